@@ -1,6 +1,19 @@
 import test from "ava"
 
-import { getAllOutputDevices, getDefaultOutputDevice } from ".."
+import {
+  AudioMonitor,
+  ErrorCode,
+  FrameOverflow,
+  SignalKind,
+  framesStream,
+  getAllOutputDevices,
+  getDefaultOutputDevice,
+  type MonitorError,
+} from ".."
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms))
+
+const peakBand = (spectrum: number[]) => spectrum.indexOf(Math.max(...spectrum))
 
 test("Output devices getter", (t) => {
   t.true(Array.isArray(getAllOutputDevices()))
@@ -9,3 +22,126 @@ test("Output devices getter", (t) => {
 test("Default output device getter", (t) => {
   t.is(typeof getDefaultOutputDevice(), "object")
 })
+
+test("Generated 1 kHz tone peaks in the expected band", async (t) => {
+  const audio = new AudioMonitor()
+  audio.setGenerator({ signal: SignalKind.Sine, frequency: 1000, sampleRate: 44100 })
+  audio.start(2048)
+  await sleep(300)
+
+  // 2048 point FFT split into 64 bands of ~344 Hz each
+  const spectrum = audio.getSpectrum(64)
+  audio.stop()
+
+  t.is(peakBand(spectrum), 2)
+})
+
+test("Generated silence produces a flat spectrum", async (t) => {
+  const audio = new AudioMonitor()
+  audio.setGenerator({ signal: SignalKind.Silence })
+  audio.start(2048)
+  await sleep(300)
+
+  const spectrum = audio.getSpectrum(16)
+  audio.stop()
+
+  t.true(spectrum.every((v) => v === 0))
+})
+
+test("Stream format is reported once started", async (t) => {
+  const audio = new AudioMonitor()
+  t.is(audio.format, null)
+
+  audio.setGenerator({ signal: SignalKind.Sine, sampleRate: 44100, channels: 2 })
+  audio.start(2048)
+  await sleep(100)
+  const format = audio.format
+  const frequencies = audio.getBandFrequencies(64)
+  audio.stop()
+
+  t.is(format?.sampleRate, 44100)
+  t.deepEqual(format?.channelNames, ["FrontLeft", "FrontRight"])
+  t.true(frequencies[2] < 1000 && 1000 < frequencies[3])
+})
+
+test("Spectrum can be written into a reused Float32Array", async (t) => {
+  const audio = new AudioMonitor()
+  audio.setGenerator({ signal: SignalKind.Sine, frequency: 1000, sampleRate: 44100 })
+  audio.start(2048)
+  await sleep(300)
+
+  const values = new Float32Array(64)
+  const written = audio.fillSpectrum(values)
+  const copy = audio.getSpectrumArray(64)
+  audio.stop()
+
+  t.is(written, 64)
+  t.is(peakBand(Array.from(values)), 2)
+  t.true(copy instanceof Float32Array)
+})
+
+test("Spectrum callback receives numbered frames", async (t) => {
+  const audio = new AudioMonitor()
+  const frames: number[] = []
+  audio.onSpectrum((frame) => frames.push(frame.sequence), { numBands: 16, maxRate: 50 })
+  audio.setGenerator({ signal: SignalKind.WhiteNoise })
+  audio.start(1024)
+  await sleep(300)
+  audio.stop()
+  audio.offSpectrum()
+
+  t.true(frames.length > 0 && frames.length <= 16)
+  t.true(frames.every((sequence, i) => i === 0 || sequence > frames[i - 1]))
+})
+
+test("Frames can be read with for await", async (t) => {
+  const audio = new AudioMonitor()
+  audio.setGenerator({ signal: SignalKind.WhiteNoise })
+  audio.start(1024)
+
+  const sequences: number[] = []
+  for await (const frame of audio.frames({ numBands: 16 })) {
+    t.is(frame.values.length, 16)
+    sequences.push(frame.sequence)
+    if (sequences.length === 3) break
+  }
+  audio.stop()
+
+  t.true(sequences.every((sequence, i) => i === 0 || sequence > sequences[i - 1]))
+})
+
+test("Frame stream pauses the analysis when blocking", async (t) => {
+  const audio = new AudioMonitor()
+  audio.setGenerator({ signal: SignalKind.WhiteNoise, realtime: false })
+  const stream = framesStream(audio, { numBands: 16, bufferSize: 2, overflow: FrameOverflow.Block })
+  audio.start(1024)
+
+  const sequences: number[] = []
+  stream.on("data", (frame) => sequences.push(frame.sequence))
+  await sleep(50)
+  stream.pause()
+  const received = sequences.length
+  await sleep(100)
+  t.is(sequences.length, received)
+
+  stream.resume()
+  await sleep(50)
+  audio.stop()
+  await new Promise((resolve) => stream.on("end", resolve))
+
+  // A generator that is not played in real time still loses no frame
+  t.true(sequences.length > received)
+  t.true(sequences.every((sequence, i) => sequence === i))
+})
+
+test("Capture errors are reported and stop the monitor", async (t) => {
+  const audio = new AudioMonitor()
+  const error = new Promise<MonitorError>((resolve) => audio.onError(resolve))
+  audio.setFile("does-not-exist.wav")
+  audio.start()
+
+  t.is((await error).code, ErrorCode.Io)
+  t.false(audio.running)
+  t.is(audio.lastError?.code, ErrorCode.Io)
+  audio.offError()
+})
@@ -0,0 +1,564 @@
+/* tslint:disable */
+/* eslint-disable */
+
+/* auto-generated by NAPI-RS */
+
+export interface AudioDevice {
+  id: string
+  /** Name of the endpoint, e.g. `Speakers (Realtek High Definition Audio)`. */
+  name: string
+  /** Name of the audio adapter, e.g. `Realtek High Definition Audio`. */
+  adapterName?: string
+  /** Description of the endpoint, e.g. `Speakers`. */
+  description?: string
+  direction: DeviceDirection
+  state: DeviceState
+  /** Default device for the console role, the one captured when no device is set. */
+  isDefault: boolean
+  defaultRoles: DefaultRoles
+  /** Shared mode format of the device, only available while it is active. */
+  mixFormat?: StreamInfo
+}
+/** Whether a device plays or records audio. */
+export const enum DeviceDirection {
+  /** An output device such as speakers or headphones. */
+  Render = 'render',
+  /** An input device such as a microphone. */
+  Capture = 'capture'
+}
+/** State of an audio endpoint. */
+export const enum DeviceState {
+  Active = 'active',
+  /** Disabled in the sound control panel. */
+  Disabled = 'disabled',
+  /** The adapter was removed or disabled in the device manager. */
+  NotPresent = 'notPresent',
+  /** Nothing is plugged into the jack of the device. */
+  Unplugged = 'unplugged'
+}
+/** Roles for which a device is the default endpoint. */
+export interface DefaultRoles {
+  /** Games, system sounds and most applications. */
+  console: boolean
+  /** Music and video playback. */
+  multimedia: boolean
+  /** Voice calls. */
+  communications: boolean
+}
+/** Which devices `getAllOutputDevices` and `getAllInputDevices` list. */
+export interface DeviceListOptions {
+  /** Only devices in one of these states. Defaults to active devices. */
+  states?: Array<DeviceState>
+}
+/** What a default device is used for. */
+export const enum DeviceRole {
+  Console = 'console',
+  Multimedia = 'multimedia',
+  Communications = 'communications'
+}
+/** Which endpoints a monitor captures. */
+export const enum DeviceKind {
+  /** What is played on an output device, the default. */
+  Loopback = 'loopback',
+  /** A microphone, line input or other capture device. */
+  Input = 'input'
+}
+export interface DeviceOptions {
+  /** Defaults to the current kind of the monitor, loopback for a new one. */
+  kind?: DeviceKind
+}
+export interface ProcessOptions {
+  /** Capture the process and its child processes (true, the default), or everything except them. */
+  includeTree?: boolean
+}
+export interface FileSourceOptions {
+  /** Deliver frames at the file's sample rate instead of as fast as possible. Defaults to true. */
+  realtime?: boolean
+}
+export const enum SignalKind {
+  Sine = 'sine',
+  MultiTone = 'multiTone',
+  Square = 'square',
+  Saw = 'saw',
+  WhiteNoise = 'whiteNoise',
+  PinkNoise = 'pinkNoise',
+  Sweep = 'sweep',
+  Impulse = 'impulse',
+  Silence = 'silence'
+}
+export interface GeneratorOptions {
+  signal: SignalKind
+  /** Frequency of tones in Hz, defaults to 1000. Start frequency of sweeps, defaults to 20. */
+  frequency?: number
+  /** Frequencies of a multi tone signal in Hz. */
+  frequencies?: Array<number>
+  /** End frequency of sweeps in Hz. Defaults to 20000. */
+  endFrequency?: number
+  /** Length of a sweep, or interval between impulses, in seconds. Defaults to 1. */
+  period?: number
+  /** Peak amplitude between 0 and 1. Defaults to 0.5. */
+  amplitude?: number
+  /** Defaults to 44100. */
+  sampleRate?: number
+  /** Defaults to 2. */
+  channels?: number
+  /** Stop after the given number of seconds instead of running forever. */
+  duration?: number
+  /** Deliver frames at the sample rate instead of as fast as possible. Defaults to true. */
+  realtime?: boolean
+}
+/** How the channels of a multi-channel stream are combined for `getSpectrum`. */
+export const enum DownmixMode {
+  /** Average of all channels. */
+  Mono = 'mono',
+  /** Average of the first two (front left and right) channels. */
+  Mid = 'mid',
+  /** Half the difference of the first two channels. */
+  Side = 'side',
+  /** Loudest value of each band over all per-channel spectra. */
+  Max = 'max'
+}
+/** Window applied to each block before the FFT. Decibel levels are compensated for the window's coherent gain. */
+export const enum WindowFunction {
+  Rectangular = 'rectangular',
+  Hann = 'hann',
+  Hamming = 'hamming',
+  Blackman = 'blackman',
+  BlackmanHarris = 'blackmanHarris',
+  FlatTop = 'flatTop',
+  /** Kaiser-Bessel window, the parameter is beta (defaults to 8.6). */
+  Kaiser = 'kaiser',
+  /** Gaussian window, the parameter is sigma relative to half the window length (defaults to 0.4). */
+  Gaussian = 'gaussian'
+}
+/** Frequency scale used to group FFT bins into bands. */
+export const enum BandScale {
+  /** Equal width bands, the default. */
+  Linear = 'linear',
+  Logarithmic = 'logarithmic',
+  Mel = 'mel',
+  Bark = 'bark',
+  /** Equivalent rectangular bandwidth rate. */
+  Erb = 'erb',
+  /** Full octave bands at the ISO 266 centre frequencies. */
+  Octave = 'octave',
+  /** Third-octave bands at the ISO 266 centre frequencies. */
+  ThirdOctave = 'thirdOctave'
+}
+export interface BandLayoutOptions {
+  scale: BandScale
+  /** Lower edge of the first band in Hz. Defaults to 0 for linear and 20 for the other scales. */
+  minFrequency?: number
+  /**
+   * Upper edge of the last band in Hz, capped at the Nyquist frequency.
+   * Defaults to 20000, or the Nyquist frequency for linear.
+   */
+  maxFrequency?: number
+}
+/** Unit of the values returned by `getSpectrum`. */
+export const enum SpectrumScale {
+  /** `log10(1 + mean magnitude)` of each band, without window gain compensation, the original scale. */
+  Log = 'log',
+  /** Band power in dBFS, where a full scale sine reads 0 dB. Never below the floor. */
+  Decibels = 'decibels',
+  /** Decibels mapped linearly from floor..ceiling to 0..1. */
+  Normalized = 'normalized'
+}
+/** Frequency weighting applied to the magnitude spectrum and the level meter. */
+export const enum FrequencyWeighting {
+  /** Flat response, the default. */
+  Z = 'z',
+  /** IEC 61672 A-weighting, approximating the loudness of quiet sounds. */
+  A = 'a',
+  /** IEC 61672 C-weighting, flatter for loud sounds. */
+  C = 'c',
+  /** ITU-R BS.468 noise weighting. */
+  Itu468 = 'itu468'
+}
+export interface ScaleOptions {
+  scale: SpectrumScale
+  /** Lowest level in dBFS. Defaults to -90. */
+  floor?: number
+  /** Level in dBFS mapped to 1 by the normalized scale. Defaults to 0. */
+  ceiling?: number
+}
+export interface FrameOptions {
+  /**
+   * Number of samples per analysis frame, rounded up to a power of two by the FFT.
+   * Defaults to the chunk size passed to `start`, 2048 if omitted.
+   */
+  fftSize?: number
+  /** Number of new samples between two frames. Defaults to the FFT size, i.e. no overlap. */
+  hopSize?: number
+}
+export interface SmoothingOptions {
+  /** Time constant in ms for rising values. Defaults to 0, i.e. no smoothing. */
+  attack?: number
+  /** Time constant in ms for falling values. Defaults to 0, i.e. no smoothing. */
+  release?: number
+  /** How long a peak marker stays in place, in ms. Defaults to 0. */
+  peakHold?: number
+  /**
+   * Acceleration of falling peak markers, in spectrum units per second².
+   * Defaults to 0, i.e. peaks drop to the current value once the hold time is over.
+   */
+  peakGravity?: number
+}
+export interface AutoGainOptions {
+  /** How far back the reference level looks, in ms. Defaults to 3000. */
+  window?: number
+  /** Percentile of the recent values mapped to 1, where 100 is the rolling maximum. Defaults to 100. */
+  percentile?: number
+  /** Track every band separately instead of the loudest band. Defaults to false. */
+  perBand?: boolean
+  /** Time constant in ms with which the gain recovers after loud passages. Defaults to 1000. */
+  adaptation?: number
+  /**
+   * Values at or below this level, in the unit of the spectrum scale, stay at 0.
+   * Defaults to the floor of the scale.
+   */
+  noiseGate?: number
+}
+export interface SpectrumFrame {
+  /** Smoothed band values, as returned by `getSpectrum`. */
+  values: Array<number>
+  /** Peak-hold marker of every band. */
+  peaks: Array<number>
+}
+export interface SpectrumCallbackOptions {
+  /** Number of bands of every frame. Defaults to 64. */
+  numBands?: number
+  /** Highest number of callbacks per second, frames in between are skipped. Unlimited by default. */
+  maxRate?: number
+}
+export interface SpectrumEvent {
+  /** Band values, as returned by `getSpectrum`. */
+  values: Array<number>
+  /** Peak-hold marker of every band. */
+  peaks: Array<number>
+  /** Time the frame was analysed, in ms since the Unix epoch. */
+  timestamp: number
+  /** Index of the frame since `start`. Gaps mean frames were skipped. */
+  sequence: number
+}
+/** What a `frames()` reader does with new frames while its buffer is full. */
+export const enum FrameOverflow {
+  /** Drop the oldest buffered frame, so that a slow reader never holds up the capture. The default. */
+  DropOldest = 'dropOldest',
+  /**
+   * Pause the analysis until the reader has room again, so that it gets every frame, e.g. of a
+   * file. Other consumers pause as well, and device audio captured meanwhile is lost.
+   */
+  Block = 'block'
+}
+export interface FrameStreamOptions {
+  /** Number of bands of every frame. Defaults to 64. */
+  numBands?: number
+  /** Highest number of frames per second, frames in between are skipped. Unlimited by default. */
+  maxRate?: number
+  /** Frames kept while the reader is busy. Defaults to 4. */
+  bufferSize?: number
+  /** What happens to new frames once `bufferSize` frames are waiting. Defaults to `dropOldest`. */
+  overflow?: FrameOverflow
+}
+/** Result of `FrameReader.next()`, as expected by the async iterator protocol. */
+export interface FrameResult {
+  value?: SpectrumEvent
+  done: boolean
+}
+/** Format requested from the capture device instead of its shared mode mix format. */
+export interface CaptureFormat {
+  /** Defaults to 48000. */
+  sampleRate?: number
+  /** Defaults to 2. */
+  channels?: number
+  /** Defaults to float. */
+  sampleType?: SampleType
+  /** Defaults to 32 for float and 16 for int samples. */
+  bitsPerSample?: number
+}
+/** Category of an error that stopped the monitor. */
+export const enum ErrorCode {
+  /** The device was not found or is no longer available. */
+  DeviceNotFound = 'deviceNotFound',
+  /** The device was unplugged, disabled or its format changed while capturing. */
+  DeviceInvalidated = 'deviceInvalidated',
+  /** The device is used in exclusive mode by another application. */
+  DeviceInUse = 'deviceInUse',
+  /** The Windows audio service is not running. */
+  ServiceNotRunning = 'serviceNotRunning',
+  /** The requested or delivered format is not supported. */
+  UnsupportedFormat = 'unsupportedFormat',
+  /** No data arrived from the device in time. */
+  Timeout = 'timeout',
+  /** Access to the device was denied. */
+  AccessDenied = 'accessDenied',
+  /** The file is not a supported wav file. */
+  InvalidFile = 'invalidFile',
+  /** Reading the file failed. */
+  Io = 'io',
+  /** Any other error. */
+  Unknown = 'unknown'
+}
+/** Error that stopped the monitor. */
+export interface MonitorError {
+  code: ErrorCode
+  message: string
+  /** HRESULT of the failing Windows call, if any. */
+  hresult?: number
+  /** Time the error occurred, in ms since the Unix epoch. */
+  timestamp: number
+}
+/** How the monitor reopens a device that was unplugged, disabled or changed its format. */
+export interface ReconnectOptions {
+  /** Defaults to true. */
+  enabled?: boolean
+  /** Delay in ms before the first attempt, doubled for every further attempt. Defaults to 100. */
+  initialDelay?: number
+  /** Longest delay in ms between two attempts. Defaults to 5000. */
+  maxDelay?: number
+  /** Attempts before giving up and reporting the error. Unlimited by default. */
+  maxAttempts?: number
+}
+/** Passed to `onReconnect` once the stream has been reopened. */
+export interface ReconnectEvent {
+  /** Error that interrupted the stream. */
+  code: ErrorCode
+  message: string
+  /** Number of attempts it took to reopen the stream. */
+  attempts: number
+  /** Time the stream was reopened, in ms since the Unix epoch. */
+  timestamp: number
+}
+/** Passed to `onDeviceSwitch` when a monitor following the default device switched to a new one. */
+export interface DeviceSwitchEvent {
+  /** Device captured until now, if it was known. */
+  previousId?: string
+  /** New default device that is captured from now on. */
+  deviceId: string
+  /** Time of the switch, in ms since the Unix epoch. */
+  timestamp: number
+}
+/** Passed to `DeviceWatcher` callbacks when a device was added, removed or changed its state. */
+export interface DeviceEvent {
+  deviceId: string
+  /** State before the change, unknown for devices added while watching. */
+  previousState?: DeviceState
+  /** State after the change, not set for added and removed devices. */
+  state?: DeviceState
+  /** Time of the notification, in ms since the Unix epoch. */
+  timestamp: number
+}
+/** Passed to `onDefaultChanged` when the default device of a role changed. */
+export interface DefaultDeviceEvent {
+  direction: DeviceDirection
+  role: DeviceRole
+  /** Previous default device, if there was one. */
+  previousId?: string
+  /** New default device, not set when no device of that direction is left. */
+  deviceId?: string
+  /** Time of the notification, in ms since the Unix epoch. */
+  timestamp: number
+}
+/** Format of the stream currently being analysed. */
+export interface StreamInfo {
+  sampleRate: number
+  channels: number
+  bitsPerSample: number
+  validBitsPerSample: number
+  sampleType: SampleType
+  channelMask: number
+  /** Speaker position of every channel, e.g. `FrontLeft`, `LowFrequency`. */
+  channelNames: Array<string>
+}
+export const enum SampleType {
+  Float = 'float',
+  Int = 'int'
+}
+export declare function getAllOutputDevices(options?: DeviceListOptions): Array<AudioDevice>
+export declare function getDefaultOutputDevice(): AudioDevice | null
+/** Microphones, line inputs and other capture endpoints. */
+export declare function getAllInputDevices(options?: DeviceListOptions): Array<AudioDevice>
+export declare function getDefaultInputDevice(): AudioDevice | null
+export declare class AudioMonitor {
+  constructor()
+  /**
+   * Capture the device with the given id. Without an id, the monitor follows the default
+   * device and switches to the new one whenever it changes. Output devices are captured in
+   * loopback mode unless `kind` is `input`; without a `kind` the current one is kept.
+   */
+  setDevice(deviceId?: string, options?: DeviceOptions): void
+  /** Whether the selected device is captured in loopback mode or as an input. */
+  get kind(): DeviceKind
+  /**
+   * Request a specific format from the capture device, or its mix format when omitted.
+   * Int samples may have 8, 16, 24 or 32 bits, float samples 32 or 64.
+   */
+  setCaptureFormat(format?: CaptureFormat): void
+  /**
+   * Capture only the audio played by the process `pid` and its children, e.g. a music player.
+   * With `includeTree: false`, everything except that process tree is captured instead, which
+   * keeps an app's own sounds out of its visuals.
+   */
+  setProcess(pid: number, options?: ProcessOptions): void
+  setFile(path: string, options?: FileSourceOptions): void
+  setGenerator(options: GeneratorOptions): void
+  start(chunkSize?: number): void
+  stop(): void
+  /**
+   * Band values of the combined channels, normalised when `setAutoGain` and smoothed when
+   * `setSmoothing` has been called.
+   */
+  getSpectrum(numBands: number): Array<number>
+  /**
+   * Same as `getSpectrum`, as a `Float32Array` instead of a JS array. Allocates a new array
+   * on every call; render loops can reuse one with `fillSpectrum` instead.
+   */
+  getSpectrumArray(numBands: number): Float32Array
+  /**
+   * Write `values.length` bands of `getSpectrum` into `values` in place, so that render
+   * loops can reuse the same array. Returns the number of bands written, which is smaller
+   * for layouts with a fixed band count such as octaves.
+   */
+  fillSpectrum(values: Float32Array): number
+  /** Same as `getSpectrum`, together with the peak-hold marker of every band. */
+  getSpectrumWithPeaks(numBands: number): SpectrumFrame
+  /**
+   * Call `callback` from the processing thread whenever a new frame has been analysed,
+   * instead of polling `getSpectrum`. Replaces the previous callback. Frames go through the
+   * same automatic gain and smoothing settings as `getSpectrum`, with their own state.
+   */
+  onSpectrum(callback: (frame: SpectrumEvent) => void, options?: SpectrumCallbackOptions): void
+  /**
+   * Async iterator over analysed frames, ending when the monitor stops. Frames that arrive
+   * while the reader is busy are buffered. Beyond `bufferSize`, the oldest are dropped, or
+   * with `overflow: 'block'` the analysis waits for the reader.
+   */
+  frames(options?: FrameStreamOptions): FrameReader & AsyncIterable<SpectrumEvent>
+  /** Remove the callback registered with `onSpectrum`. */
+  offSpectrum(): void
+  /**
+   * Call `callback` when capturing fails for good, e.g. a file that cannot be read, or an
+   * unplugged device once reconnecting is disabled or has given up. The monitor has stopped
+   * by then and `lastError` holds the same error.
+   */
+  onError(callback: (error: MonitorError) => void): void
+  /** Remove the callback registered with `onError`. */
+  offError(): void
+  /**
+   * Configure how a device that was unplugged, disabled or changed its format is reopened.
+   * The same device is used if it comes back, the new default device otherwise.
+   * Delays must be finite and at least 0.
+   */
+  setReconnect(options: ReconnectOptions): void
+  /** Call `callback` when the stream has been reopened after the device was lost. */
+  onReconnect(callback: (event: ReconnectEvent) => void): void
+  /** Remove the callback registered with `onReconnect`. */
+  offReconnect(): void
+  /** Call `callback` when a monitor following the default device switched to a new device. */
+  onDeviceSwitch(callback: (event: DeviceSwitchEvent) => void): void
+  /** Remove the callback registered with `onDeviceSwitch`. */
+  offDeviceSwitch(): void
+  /** Error that stopped the monitor, `null` if it is running or was stopped normally. */
+  get lastError(): MonitorError | null
+  /**
+   * Configure attack/release smoothing and peak-hold markers of `getSpectrum`.
+   * All values must be finite and at least 0.
+   */
+  setSmoothing(options: SmoothingOptions): void
+  /**
+   * Normalise `getSpectrum` to 0-1 relative to the recent level, or disable it when omitted.
+   * `window` must be above 0, `adaptation` at least 0, `percentile` between 0 and 100 and
+   * `noiseGate` finite.
+   */
+  setAutoGain(options?: AutoGainOptions): void
+  /** Band values of a single channel, without automatic gain or smoothing. */
+  getChannelSpectrum(channel: number, numBands: number): Array<number>
+  setDownmix(mode: DownmixMode): void
+  get downmix(): DownmixMode
+  /**
+   * Select the window function. `parameter` is beta for Kaiser windows, between 0 and 50,
+   * and sigma for Gaussian windows, above 0.
+   */
+  setWindow(window: WindowFunction, parameter?: number): void
+  get window(): WindowFunction
+  /**
+   * Choose how FFT bins are grouped into bands, e.g. logarithmic or third-octave bands.
+   * The maximum frequency must be above the minimum, and at least 1 Hz for logarithmic
+   * and octave layouts.
+   */
+  setBandLayout(layout: BandLayoutOptions): void
+  get bandScale(): BandScale
+  /** Choose the unit of the spectrum values: the original log scale, dBFS, or 0-1 between a dB floor and ceiling. */
+  setScale(options: ScaleOptions): void
+  get scale(): SpectrumScale
+  /** Weight the spectrum and the level meter by a frequency curve, e.g. A-weighting. */
+  setWeighting(weighting: FrequencyWeighting): void
+  get weighting(): FrequencyWeighting
+  /**
+   * Weighted level of the combined channels in dBFS, where a full scale sine reads 0 dB.
+   * Never below the floor set with `setScale`.
+   */
+  getLevel(): number
+  get channels(): number
+  /**
+   * Centre frequency in Hz of every band returned by `getSpectrum`.
+   * Assumes 48 kHz until the stream has been opened.
+   */
+  getBandFrequencies(numBands: number): Array<number>
+  /** Format of the stream being analysed, `null` until the source has been opened. */
+  get format(): StreamInfo | null
+  get sampleRate(): number | null
+  /** Id of the device being captured, or of the one selected with `setDevice` until started. */
+  get currentDeviceId(): string | null
+  /** Whether the monitor follows the default device of its kind, i.e. `setDevice` was called without an id. */
+  get followsDefaultDevice(): boolean
+  get running(): boolean
+  get chunkSize(): number
+  /**
+   * Set the analysis frame length and how many new samples arrive between two frames,
+   * e.g. an 8192-point FFT every 368 samples for 120 updates per second at 44.1 kHz.
+   * Stops the monitor if it is running.
+   */
+  setFrameOptions(options: FrameOptions): void
+  /** Number of new samples between two analysis frames. */
+  get hopSize(): number
+}
+/** Async iterator over analysed frames, returned by `AudioMonitor.frames()`. */
+export declare class FrameReader {
+  /** Resolve with the next frame, or `done` once the monitor stops or the reader is closed. */
+  next(): Promise<FrameResult>
+  /** Stop reading, e.g. when leaving a `for await` loop early. */
+  return(): Promise<FrameResult>
+}
+/**
+ * Reports audio devices that are added, removed, plugged in or disabled, and changes of the
+ * default devices, without polling the device list.
+ */
+export declare class DeviceWatcher {
+  /** Start watching the audio endpoints of the system. */
+  constructor()
+  /**
+   * Watch again after `stop`, keeping the registered callbacks. Changes that happened while
+   * stopped are not reported.
+   */
+  start(): void
+  /** Stop watching. No callbacks are called afterwards. */
+  stop(): void
+  /** Call `callback` when a new audio device was installed. */
+  onDeviceAdded(callback: (event: DeviceEvent) => void): void
+  /** Remove the callback registered with `onDeviceAdded`. */
+  offDeviceAdded(): void
+  /** Call `callback` when an audio device was uninstalled. */
+  onDeviceRemoved(callback: (event: DeviceEvent) => void): void
+  /** Remove the callback registered with `onDeviceRemoved`. */
+  offDeviceRemoved(): void
+  /** Call `callback` when a device was plugged in, unplugged, enabled or disabled. */
+  onDeviceStateChanged(callback: (event: DeviceEvent) => void): void
+  /** Remove the callback registered with `onDeviceStateChanged`. */
+  offDeviceStateChanged(): void
+  /** Call `callback` when the default input or output device of a role changed. */
+  onDefaultChanged(callback: (event: DefaultDeviceEvent) => void): void
+  /** Remove the callback registered with `onDefaultChanged`. */
+  offDefaultChanged(): void
+}
@@ -1,23 +1,12 @@
-/* tslint:disable */
-/* eslint-disable */
+import type { Readable } from "stream"
 
-/* auto-generated by NAPI-RS */
+import type { AudioMonitor, FrameStreamOptions } from "./binding"
 
-export interface AudioDevice {
-  id: string
-  name: string
-  state: number
-  isDefault: boolean
-}
-export declare function getAllOutputDevices(): Array<AudioDevice>
-export declare function getDefaultOutputDevice(): AudioDevice | null
-export declare class AudioMonitor {
-  constructor()
-  setDevice(deviceId?: string): void
-  start(chunkSize?: number): void
-  stop(): void
-  getSpectrum(numBands: number): Array<number>
-  get currentDeviceId(): string | null
-  get running(): boolean
-  get chunkSize(): number
-}
+export * from "./binding"
+
+/**
+ * Object mode `Readable` of the frames of `monitor.frames(options)`. Frames are only pulled
+ * from the reader while the stream is flowing, so a paused stream lets the reader's buffer
+ * fill up and its `overflow` policy apply. Destroying the stream closes the reader.
+ */
+export declare function framesStream(monitor: AudioMonitor, options?: FrameStreamOptions): Readable
@@ -1,317 +1,14 @@
-/* tslint:disable */
-/* eslint-disable */
-/* prettier-ignore */
+const { Readable } = require("stream")
 
-/* auto-generated by NAPI-RS */
+const binding = require("./binding.js")
 
-const { existsSync, readFileSync } = require('fs')
-const { join } = require('path')
-
-const { platform, arch } = process
-
-let nativeBinding = null
-let localFileExisted = false
-let loadError = null
-
-function isMusl() {
-  // For Node 10
-  if (!process.report || typeof process.report.getReport !== 'function') {
-    try {
-      const lddPath = require('child_process').execSync('which ldd').toString().trim()
-      return readFileSync(lddPath, 'utf8').includes('musl')
-    } catch (e) {
-      return true
-    }
-  } else {
-    const { glibcVersionRuntime } = process.report.getReport().header
-    return !glibcVersionRuntime
-  }
-}
-
-switch (platform) {
-  case 'android':
-    switch (arch) {
-      case 'arm64':
-        localFileExisted = existsSync(join(__dirname, 'windows-audio-visualisation.android-arm64.node'))
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.android-arm64.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-android-arm64')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      case 'arm':
-        localFileExisted = existsSync(join(__dirname, 'windows-audio-visualisation.android-arm-eabi.node'))
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.android-arm-eabi.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-android-arm-eabi')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      default:
-        throw new Error(`Unsupported architecture on Android ${arch}`)
-    }
-    break
-  case 'win32':
-    switch (arch) {
-      case 'x64':
-        localFileExisted = existsSync(
-          join(__dirname, 'windows-audio-visualisation.win32-x64-msvc.node')
-        )
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.win32-x64-msvc.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-win32-x64-msvc')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      case 'ia32':
-        localFileExisted = existsSync(
-          join(__dirname, 'windows-audio-visualisation.win32-ia32-msvc.node')
-        )
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.win32-ia32-msvc.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-win32-ia32-msvc')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      case 'arm64':
-        localFileExisted = existsSync(
-          join(__dirname, 'windows-audio-visualisation.win32-arm64-msvc.node')
-        )
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.win32-arm64-msvc.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-win32-arm64-msvc')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      default:
-        throw new Error(`Unsupported architecture on Windows: ${arch}`)
-    }
-    break
-  case 'darwin':
-    localFileExisted = existsSync(join(__dirname, 'windows-audio-visualisation.darwin-universal.node'))
-    try {
-      if (localFileExisted) {
-        nativeBinding = require('./windows-audio-visualisation.darwin-universal.node')
-      } else {
-        nativeBinding = require('@coooookies/windows-audio-visualisation-darwin-universal')
-      }
-      break
-    } catch {}
-    switch (arch) {
-      case 'x64':
-        localFileExisted = existsSync(join(__dirname, 'windows-audio-visualisation.darwin-x64.node'))
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.darwin-x64.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-darwin-x64')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      case 'arm64':
-        localFileExisted = existsSync(
-          join(__dirname, 'windows-audio-visualisation.darwin-arm64.node')
-        )
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.darwin-arm64.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-darwin-arm64')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      default:
-        throw new Error(`Unsupported architecture on macOS: ${arch}`)
-    }
-    break
-  case 'freebsd':
-    if (arch !== 'x64') {
-      throw new Error(`Unsupported architecture on FreeBSD: ${arch}`)
-    }
-    localFileExisted = existsSync(join(__dirname, 'windows-audio-visualisation.freebsd-x64.node'))
-    try {
-      if (localFileExisted) {
-        nativeBinding = require('./windows-audio-visualisation.freebsd-x64.node')
-      } else {
-        nativeBinding = require('@coooookies/windows-audio-visualisation-freebsd-x64')
-      }
-    } catch (e) {
-      loadError = e
-    }
-    break
-  case 'linux':
-    switch (arch) {
-      case 'x64':
-        if (isMusl()) {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-x64-musl.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-x64-musl.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-x64-musl')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        } else {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-x64-gnu.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-x64-gnu.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-x64-gnu')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        }
-        break
-      case 'arm64':
-        if (isMusl()) {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-arm64-musl.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-arm64-musl.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-arm64-musl')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        } else {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-arm64-gnu.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-arm64-gnu.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-arm64-gnu')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        }
-        break
-      case 'arm':
-        if (isMusl()) {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-arm-musleabihf.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-arm-musleabihf.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-arm-musleabihf')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        } else {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-arm-gnueabihf.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-arm-gnueabihf.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-arm-gnueabihf')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        }
-        break
-      case 'riscv64':
-        if (isMusl()) {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-riscv64-musl.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-riscv64-musl.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-riscv64-musl')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        } else {
-          localFileExisted = existsSync(
-            join(__dirname, 'windows-audio-visualisation.linux-riscv64-gnu.node')
-          )
-          try {
-            if (localFileExisted) {
-              nativeBinding = require('./windows-audio-visualisation.linux-riscv64-gnu.node')
-            } else {
-              nativeBinding = require('@coooookies/windows-audio-visualisation-linux-riscv64-gnu')
-            }
-          } catch (e) {
-            loadError = e
-          }
-        }
-        break
-      case 's390x':
-        localFileExisted = existsSync(
-          join(__dirname, 'windows-audio-visualisation.linux-s390x-gnu.node')
-        )
-        try {
-          if (localFileExisted) {
-            nativeBinding = require('./windows-audio-visualisation.linux-s390x-gnu.node')
-          } else {
-            nativeBinding = require('@coooookies/windows-audio-visualisation-linux-s390x-gnu')
-          }
-        } catch (e) {
-          loadError = e
-        }
-        break
-      default:
-        throw new Error(`Unsupported architecture on Linux: ${arch}`)
-    }
-    break
-  default:
-    throw new Error(`Unsupported OS: ${platform}, architecture: ${arch}`)
-}
-
-if (!nativeBinding) {
-  if (loadError) {
-    throw loadError
-  }
-  throw new Error(`Failed to load native binding`)
+/**
+ * Object mode `Readable` of the frames of `monitor.frames(options)`. Frames are only pulled
+ * from the reader while the stream is flowing, so a paused stream lets the reader's buffer
+ * fill up and its `overflow` policy apply. Destroying the stream closes the reader.
+ */
+function framesStream(monitor, options) {
+  return Readable.from(monitor.frames(options), { objectMode: true, highWaterMark: 1 })
 }
 
-const { AudioMonitor, getAllOutputDevices, getDefaultOutputDevice } = nativeBinding
-
-module.exports.AudioMonitor = AudioMonitor
-module.exports.getAllOutputDevices = getAllOutputDevices
-module.exports.getDefaultOutputDevice = getDefaultOutputDevice
+module.exports = { ...binding, framesStream }
@@ -3,5 +3,23 @@ const require = createRequire(import.meta.url)
 export const {
   getAllOutputDevices,
   getDefaultOutputDevice,
+  getAllInputDevices,
+  getDefaultInputDevice,
   AudioMonitor,
+  DeviceDirection,
+  DeviceState,
+  DeviceRole,
+  DeviceKind,
+  FrameReader,
+  DeviceWatcher,
+  SignalKind,
+  DownmixMode,
+  WindowFunction,
+  BandScale,
+  SpectrumScale,
+  FrequencyWeighting,
+  FrameOverflow,
+  ErrorCode,
+  SampleType,
+  framesStream,
 } = require("./index.js")
@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::smoothing::coefficient;
+use crate::types::AutoGainOptions;
+use crate::utils::check_value;
+use napi::Result;
+
+/// Automatic gain control mapping band values to 0-1 relative to their recent level.
+///
+/// The reference level is a percentile of the values seen during the last `window` seconds,
+/// either per band or of the loudest band. It follows louder passages immediately and
+/// relaxes towards quieter ones with the `adaptation` time constant.
+#[derive(Clone)]
+pub struct AutoGain {
+  window: f32,
+  percentile: f32,
+  per_band: bool,
+  adaptation: f32,
+  gate: Option<f32>,
+  // 每帧的电平及其距今的时间（秒）
+  history: VecDeque<(f32, Vec<f32>)>,
+  references: Vec<f32>,
+  output: Vec<f32>,
+  scratch: Vec<f32>,
+  last_update: Option<Instant>,
+}
+
+impl AutoGain {
+  /// `window` and `adaptation` are in seconds, `percentile` between 0 and 100 where 100 tracks
+  /// the rolling maximum. Values at or below `gate` stay at zero; without a gate, the floor of
+  /// the spectrum scale passed to [AutoGain::advance] is used.
+  pub fn new(
+    window: f32,
+    percentile: f32,
+    per_band: bool,
+    adaptation: f32,
+    gate: Option<f32>,
+  ) -> Self {
+    AutoGain {
+      window: window.max(0.0),
+      percentile: percentile.clamp(0.0, 100.0),
+      per_band,
+      adaptation: adaptation.max(0.0),
+      gate,
+      history: VecDeque::new(),
+      references: Vec::new(),
+      output: Vec::new(),
+      scratch: Vec::new(),
+      last_update: None,
+    }
+  }
+
+  /// Automatic gain configured with `setAutoGain`, whose times are in milliseconds. `window`
+  /// must be above 0, `adaptation` at least 0, `percentile` between 0 and 100 and `noiseGate`
+  /// finite.
+  pub fn from_options(options: AutoGainOptions) -> Result<Self> {
+    check_value("window", options.window, |ms| ms > 0.0, " above 0")?;
+    check_value(
+      "adaptation",
+      options.adaptation,
+      |ms| ms >= 0.0,
+      " of at least 0",
+    )?;
+    check_value(
+      "percentile",
+      options.percentile,
+      |percentile| (0.0..=100.0).contains(&percentile),
+      " between 0 and 100",
+    )?;
+    check_value("noiseGate", options.noise_gate, |_| true, "")?;
+
+    Ok(AutoGain::new(
+      options.window.unwrap_or(3000.0) as f32 / 1000.0,
+      options.percentile.unwrap_or(100.0) as f32,
+      options.per_band.unwrap_or(false),
+      options.adaptation.unwrap_or(1000.0) as f32 / 1000.0,
+      options.noise_gate.map(|gate| gate as f32),
+    ))
+  }
+
+  /// Advance by the wall-clock time elapsed since the previous update.
+  pub fn update(&mut self, input: &[f32], floor: f32) -> &[f32] {
+    let now = Instant::now();
+    let elapsed = self
+      .last_update
+      .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+    self.last_update = Some(now);
+    self.advance(input, floor, elapsed)
+  }
+
+  /// Add a frame `elapsed` seconds after the previous one and return it normalised to 0-1.
+  /// `floor` is the lowest value of the spectrum scale, e.g. 0 for the log scale.
+  pub fn advance(&mut self, input: &[f32], floor: f32, elapsed: f32) -> &[f32] {
+    let gate = self.gate.unwrap_or(floor);
+
+    // 频段数量变化时丢弃历史
+    if self.output.len() != input.len() {
+      self.history.clear();
+      self.references.clear();
+      self.output = vec![0.0; input.len()];
+    }
+
+    for (age, _) in self.history.iter_mut() {
+      *age += elapsed;
+    }
+    // 复用过期帧的缓冲区，窗口填满后不再分配
+    let mut levels = Vec::new();
+    while self
+      .history
+      .front()
+      .is_some_and(|(age, _)| *age > self.window)
+    {
+      levels = self.history.pop_front().unwrap().1;
+    }
+
+    levels.clear();
+    if self.per_band {
+      levels.extend_from_slice(input);
+    } else {
+      levels.push(input.iter().copied().fold(gate, f32::max));
+    }
+    self.history.push_back((0.0, levels));
+
+    let fresh = self.references.is_empty();
+    let count = if self.per_band { input.len() } else { 1 };
+    self.references.resize(count, gate);
+    let rate = coefficient(self.adaptation, elapsed);
+
+    for (j, reference) in self.references.iter_mut().enumerate() {
+      self.scratch.clear();
+      self
+        .scratch
+        .extend(self.history.iter().map(|(_, levels)| levels[j]));
+      let target = percentile(&mut self.scratch, self.percentile);
+
+      // 音量变大时立即跟上，变小时按适应速度缓慢放大
+      *reference = if fresh || target >= *reference {
+        target
+      } else {
+        *reference + (target - *reference) * rate
+      };
+    }
+
+    for (i, (value, &level)) in self.output.iter_mut().zip(input.iter()).enumerate() {
+      let reference = self.references[if self.per_band { i } else { 0 }];
+      *value = if level <= gate || reference <= gate {
+        0.0
+      } else {
+        ((level - gate) / (reference - gate)).min(1.0)
+      };
+    }
+
+    &self.output
+  }
+}
+
+// 最近秩法求百分位数，会打乱 `values` 的顺序
+fn percentile(values: &mut [f32], percentile: f32) -> f32 {
+  if values.is_empty() {
+    return 0.0;
+  }
+  let index = ((percentile / 100.0) * (values.len() - 1) as f32).round() as usize;
+  let (_, value, _) = values.select_nth_unstable_by(index, f32::total_cmp);
+  *value
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_alloc::count_allocations;
+
+  #[test]
+  fn quiet_and_loud_inputs_reach_full_scale() {
+    for level in [0.05, 0.5, 5.0] {
+      let mut agc = AutoGain::new(3.0, 100.0, false, 1.0, None);
+      let mut output = Vec::new();
+      for _ in 0..10 {
+        output = agc.advance(&[level, level / 2.0, 0.0], 0.0, 0.05).to_vec();
+      }
+      assert_eq!(output, vec![1.0, 0.5, 0.0], "{}", level);
+    }
+  }
+
+  #[test]
+  fn gain_recovers_after_loud_passages() {
+    let mut agc = AutoGain::new(0.5, 100.0, false, 1.0, None);
+    agc.advance(&[4.0], 0.0, 0.0);
+    // 响亮的片段仍在窗口内时参考电平保持不变
+    assert_eq!(agc.advance(&[1.0], 0.0, 0.25), &[0.25]);
+
+    // 离开窗口后按 1 秒的时间常数逐渐放大
+    let mut output = 0.0;
+    for _ in 0..10 {
+      output = agc.advance(&[1.0], 0.0, 0.1)[0];
+    }
+    assert!(output > 0.3 && output < 0.9, "{}", output);
+    for _ in 0..100 {
+      output = agc.advance(&[1.0], 0.0, 0.1)[0];
+    }
+    assert!((output - 1.0).abs() < 1e-3, "{}", output);
+  }
+
+  #[test]
+  fn percentile_ignores_short_transients() {
+    let mut agc = AutoGain::new(10.0, 50.0, false, 1.0, None);
+    for _ in 0..20 {
+      agc.advance(&[1.0], 0.0, 0.05);
+    }
+    // 单个尖峰被截断到 1，不会拉低之后的输出
+    assert_eq!(agc.advance(&[8.0], 0.0, 0.05), &[1.0]);
+    assert_eq!(agc.advance(&[1.0], 0.0, 0.05), &[1.0]);
+  }
+
+  #[test]
+  fn per_band_references_are_independent() {
+    let mut agc = AutoGain::new(3.0, 100.0, true, 1.0, None);
+    assert_eq!(agc.advance(&[0.1, 2.0], 0.0, 0.0), &[1.0, 1.0]);
+    assert_eq!(agc.advance(&[0.05, 1.0], 0.0, 0.05), &[0.5, 0.5]);
+  }
+
+  #[test]
+  fn noise_gate_keeps_silence_at_zero() {
+    // 分贝刻度下以下限为门限
+    let mut agc = AutoGain::new(3.0, 100.0, false, 1.0, None);
+    for _ in 0..10 {
+      assert_eq!(agc.advance(&[-90.0, -90.0], -90.0, 0.05), &[0.0, 0.0]);
+    }
+    assert_eq!(agc.advance(&[-30.0, -60.0], -90.0, 0.05), &[1.0, 0.5]);
+
+    let mut agc = AutoGain::new(3.0, 100.0, false, 1.0, Some(0.01));
+    for _ in 0..10 {
+      assert_eq!(agc.advance(&[0.005, 0.002], 0.0, 0.05), &[0.0, 0.0]);
+    }
+  }
+
+  #[test]
+  fn history_is_recycled() {
+    for per_band in [false, true] {
+      let mut agc = AutoGain::new(0.5, 90.0, per_band, 1.0, None);
+      let input = [0.5; 64];
+      for _ in 0..20 {
+        agc.advance(&input, 0.0, 0.05);
+      }
+      let allocated = count_allocations(|| {
+        for _ in 0..100 {
+          agc.advance(&input, 0.0, 0.05);
+        }
+      });
+      assert_eq!(allocated, 0, "{}", per_band);
+    }
+  }
+
+  fn options(window: f64, adaptation: f64, percentile: f64) -> AutoGainOptions {
+    AutoGainOptions {
+      window: Some(window),
+      percentile: Some(percentile),
+      per_band: None,
+      adaptation: Some(adaptation),
+      noise_gate: None,
+    }
+  }
+
+  #[test]
+  fn rejects_invalid_auto_gain() {
+    for (options, message) in [
+      (
+        options(0.0, 1000.0, 100.0),
+        "window 0 must be a finite number above 0",
+      ),
+      (
+        options(f64::NAN, 1000.0, 100.0),
+        "window NaN must be a finite number above 0",
+      ),
+      (
+        options(3000.0, -1.0, 100.0),
+        "adaptation -1 must be a finite number of at least 0",
+      ),
+      (
+        options(3000.0, f64::INFINITY, 100.0),
+        "adaptation inf must be a finite number of at least 0",
+      ),
+      (
+        options(3000.0, 1000.0, 101.0),
+        "percentile 101 must be a finite number between 0 and 100",
+      ),
+      (
+        AutoGainOptions {
+          noise_gate: Some(f64::NAN),
+          ..options(3000.0, 1000.0, 100.0)
+        },
+        "noiseGate NaN must be a finite number",
+      ),
+    ] {
+      let err = AutoGain::from_options(options).err().unwrap();
+      assert_eq!(err.reason, message);
+    }
+
+    let agc = AutoGain::from_options(AutoGainOptions {
+      noise_gate: Some(-60.0),
+      ..options(3000.0, 0.0, 0.0)
+    })
+    .unwrap();
+    assert_eq!(
+      (agc.window, agc.adaptation, agc.gate),
+      (3.0, 0.0, Some(-60.0))
+    );
+  }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use crate::types::ReconnectOptions;
+use crate::utils::check_value;
+use napi::{Error, Result, Status};
+
+/// Exponential backoff between attempts to reopen a capture source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+  initial: Duration,
+  max: Duration,
+  max_attempts: Option<u32>,
+}
+
+impl Backoff {
+  /// Wait `initial` before the first attempt and twice as long before every further one,
+  /// up to `max`. Gives up after `max_attempts` attempts, or never when `None`.
+  pub fn new(initial: Duration, max: Duration, max_attempts: Option<u32>) -> Self {
+    Backoff {
+      initial,
+      max: max.max(initial),
+      max_attempts,
+    }
+  }
+
+  /// Backoff configured with `setReconnect`, or `None` when reconnecting is disabled.
+  /// Delays are in milliseconds and must be finite and at least 0.
+  pub fn from_options(options: ReconnectOptions) -> Result<Option<Self>> {
+    if options.enabled == Some(false) {
+      return Ok(None);
+    }
+    let delay = |name: &str, ms: Option<f64>, default: u64| {
+      check_value(name, ms, |ms| ms >= 0.0, " of at least 0")?;
+      match ms {
+        // 超出 Duration 范围的值同样被拒绝
+        Some(ms) => Duration::try_from_secs_f64(ms / 1000.0)
+          .map_err(|e| Error::new(Status::InvalidArg, format!("{} {} ms: {}", name, ms, e))),
+        None => Ok(Duration::from_millis(default)),
+      }
+    };
+    Ok(Some(Backoff::new(
+      delay("initialDelay", options.initial_delay, 100)?,
+      delay("maxDelay", options.max_delay, 5000)?,
+      options.max_attempts,
+    )))
+  }
+
+  /// Delay before attempt `attempt`, counted from 1, or `None` once all attempts are used.
+  pub fn delay(&self, attempt: u32) -> Option<Duration> {
+    if self.max_attempts.is_some_and(|max| attempt > max) {
+      return None;
+    }
+    // 指数超过 16 时早已达到上限
+    let factor = 1u32 << attempt.saturating_sub(1).min(16);
+    Some(self.initial.saturating_mul(factor).min(self.max))
+  }
+}
+
+impl Default for Backoff {
+  fn default() -> Self {
+    Backoff::new(Duration::from_millis(100), Duration::from_secs(5), None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn delay_doubles_up_to_the_maximum() {
+    let backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1), None);
+    let delays: Vec<u128> = (1..=6)
+      .map(|attempt| backoff.delay(attempt).unwrap().as_millis())
+      .collect();
+    assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+    assert_eq!(backoff.delay(1000), Some(Duration::from_secs(1)));
+  }
+
+  #[test]
+  fn gives_up_after_max_attempts() {
+    let backoff = Backoff::new(Duration::from_millis(10), Duration::from_secs(1), Some(2));
+    assert!(backoff.delay(2).is_some());
+    assert_eq!(backoff.delay(3), None);
+  }
+
+  fn options(initial_delay: f64, max_delay: f64) -> ReconnectOptions {
+    ReconnectOptions {
+      enabled: None,
+      initial_delay: Some(initial_delay),
+      max_delay: Some(max_delay),
+      max_attempts: None,
+    }
+  }
+
+  #[test]
+  fn rejects_invalid_delays() {
+    for (options, message) in [
+      (
+        options(-5.0, 10.0),
+        "initialDelay -5 must be a finite number of at least 0",
+      ),
+      (
+        options(1.0, f64::NAN),
+        "maxDelay NaN must be a finite number of at least 0",
+      ),
+      (
+        options(1.0, f64::INFINITY),
+        "maxDelay inf must be a finite number of at least 0",
+      ),
+    ] {
+      assert_eq!(Backoff::from_options(options).unwrap_err().reason, message);
+    }
+    let err = Backoff::from_options(options(1.0, 1e23)).unwrap_err();
+    assert!(
+      err
+        .reason
+        .starts_with("maxDelay 100000000000000000000000 ms: "),
+      "{}",
+      err.reason
+    );
+
+    assert_eq!(
+      Backoff::from_options(options(0.0, 10.0)).unwrap(),
+      Some(Backoff::new(
+        Duration::ZERO,
+        Duration::from_millis(10),
+        None
+      ))
+    );
+    let disabled = ReconnectOptions {
+      enabled: Some(false),
+      ..options(-5.0, 10.0)
+    };
+    assert_eq!(Backoff::from_options(disabled).unwrap(), None);
+  }
+}
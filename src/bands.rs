@@ -0,0 +1,346 @@
+use crate::types::{BandLayoutOptions, BandScale};
+use crate::utils::check_value;
+use napi::{Error, Result, Status};
+
+// ISO 266 R10 优选数，用作 1/3 倍频程的标称中心频率
+const PREFERRED_NUMBERS: [f32; 10] = [1.0, 1.25, 1.6, 2.0, 2.5, 3.15, 4.0, 5.0, 6.3, 8.0];
+
+/// Frequency range of one analysis band, in Hz.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Band {
+  pub low: f32,
+  pub centre: f32,
+  pub high: f32,
+}
+
+/// How the FFT bins are grouped into bands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BandLayout {
+  pub scale: BandScale,
+  /// Lower edge of the first band. Defaults to 0 Hz for linear and 20 Hz for the other scales.
+  pub min_frequency: Option<f32>,
+  /// Upper edge of the last band, capped at the Nyquist frequency. Defaults to 20 kHz, or Nyquist for linear.
+  pub max_frequency: Option<f32>,
+}
+
+impl Default for BandLayout {
+  fn default() -> Self {
+    BandLayout {
+      scale: BandScale::Linear,
+      min_frequency: None,
+      max_frequency: None,
+    }
+  }
+}
+
+impl BandLayout {
+  /// Layout chosen with `setBandLayout`. The maximum frequency must be above the minimum,
+  /// and at least 1 Hz for logarithmic and octave layouts.
+  pub fn from_options(options: BandLayoutOptions) -> Result<Self> {
+    let at_least_zero = |frequency: f64| frequency >= 0.0;
+    check_value(
+      "minFrequency",
+      options.min_frequency,
+      at_least_zero,
+      " of at least 0",
+    )?;
+    check_value(
+      "maxFrequency",
+      options.max_frequency,
+      at_least_zero,
+      " of at least 0",
+    )?;
+    let layout = BandLayout {
+      scale: options.scale,
+      min_frequency: options.min_frequency.map(|f| f as f32),
+      max_frequency: options.max_frequency.map(|f| f as f32),
+    };
+
+    // 检查补上默认值之后的范围，线性布局未指定上限时延伸到奈奎斯特频率
+    let (min, max) = layout.range();
+    if let Some(max) = max {
+      let logarithmic = matches!(
+        layout.scale,
+        BandScale::Logarithmic | BandScale::Octave | BandScale::ThirdOctave
+      );
+      if logarithmic && max < 1.0 {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "Maximum frequency {} Hz must be at least 1 Hz for logarithmic and octave layouts",
+            max
+          ),
+        ));
+      }
+      // 对数刻度的频段从 1 Hz 开始
+      let min = match layout.scale {
+        BandScale::Logarithmic => min.max(1.0),
+        _ => min,
+      };
+      if max <= min {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "Maximum frequency {} Hz must be above minimum frequency {} Hz",
+            max, min
+          ),
+        ));
+      }
+    }
+    Ok(layout)
+  }
+
+  /// Lower and upper edge with the defaults applied, before capping at the Nyquist frequency.
+  /// The upper edge is `None` for linear layouts that end at Nyquist.
+  pub fn range(&self) -> (f32, Option<f32>) {
+    let (default_min, default_max) = match self.scale {
+      BandScale::Linear => (0.0, None),
+      _ => (20.0, Some(20000.0)),
+    };
+    (
+      self.min_frequency.unwrap_or(default_min),
+      self.max_frequency.or(default_max),
+    )
+  }
+
+  /// Compute the bands for a stream at `sample_rate`.
+  ///
+  /// Octave and third-octave layouts use the standard bands whose centre lies in the
+  /// frequency range, so their count does not depend on `num_bands`.
+  pub fn bands(&self, num_bands: usize, sample_rate: u32) -> Vec<Band> {
+    let nyquist = sample_rate as f32 / 2.0;
+    let (min, max) = self.range();
+    // min/max 会忽略 NaN，不会像 clamp 那样在范围无效时 panic
+    let max = max.unwrap_or(nyquist).min(nyquist).max(0.0);
+    let min = min.max(0.0).min(max);
+
+    match self.scale {
+      BandScale::Octave => fractional_octaves(3, min, max),
+      BandScale::ThirdOctave => fractional_octaves(1, min, max),
+      scale => {
+        if num_bands == 0 {
+          return Vec::new();
+        }
+        // 对数刻度无法表示 0 Hz
+        let min = match scale {
+          BandScale::Logarithmic => min.max(1.0),
+          _ => min,
+        };
+        let (low, high) = (to_scale(scale, min), to_scale(scale, max));
+        let step = (high - low) / num_bands as f32;
+        (0..num_bands)
+          .map(|i| {
+            let start = low + step * i as f32;
+            Band {
+              low: from_scale(scale, start),
+              centre: from_scale(scale, start + step / 2.0),
+              high: from_scale(scale, start + step),
+            }
+          })
+          .collect()
+      }
+    }
+  }
+}
+
+fn to_scale(scale: BandScale, frequency: f32) -> f32 {
+  match scale {
+    BandScale::Logarithmic => frequency.ln(),
+    BandScale::Mel => 2595.0 * (1.0 + frequency / 700.0).log10(),
+    // Traunmüller 1990
+    BandScale::Bark => 26.81 * frequency / (1960.0 + frequency) - 0.53,
+    // Glasberg & Moore 1990
+    BandScale::Erb => 21.4 * (1.0 + 0.00437 * frequency).log10(),
+    _ => frequency,
+  }
+}
+
+fn from_scale(scale: BandScale, value: f32) -> f32 {
+  match scale {
+    BandScale::Logarithmic => value.exp(),
+    BandScale::Mel => 700.0 * (10f32.powf(value / 2595.0) - 1.0),
+    BandScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+    BandScale::Erb => (10f32.powf(value / 21.4) - 1.0) / 0.00437,
+    _ => value,
+  }
+}
+
+// 以 10 为底的倍频程（IEC 61260），`step` 为每个频段包含的 1/3 倍频程数
+fn fractional_octaves(step: i32, min: f32, max: f32) -> Vec<Band> {
+  let ratio = 10f32.powf(step as f32 / 20.0);
+  // 1/3 倍频程编号 n 的中心频率为 1000 * 10^(n/10)
+  let first = (10.0 * (min.max(1.0) / 1000.0).log10()).ceil() as i32;
+  let last = (10.0 * (max.max(1.0) / 1000.0).log10()).floor() as i32;
+
+  (first..=last)
+    .filter(|n| n.rem_euclid(step) == 0)
+    .map(|n| {
+      let exact = 1000.0 * 10f32.powf(n as f32 / 10.0);
+      Band {
+        low: exact / ratio,
+        centre: nominal_frequency(n),
+        high: exact * ratio,
+      }
+    })
+    .collect()
+}
+
+// 第 n 个 1/3 倍频程的标称中心频率，例如 n = 0 时为 1000 Hz，n = 5 时为 3150 Hz
+fn nominal_frequency(n: i32) -> f32 {
+  let index = n.rem_euclid(10) as usize;
+  let decade = n.div_euclid(10) + 3;
+  PREFERRED_NUMBERS[index] * 10f32.powi(decade)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn layout(scale: BandScale) -> BandLayout {
+    BandLayout {
+      scale,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn linear_covers_zero_to_nyquist() {
+    let bands = layout(BandScale::Linear).bands(64, 44100);
+    assert_eq!(bands.len(), 64);
+    assert_eq!(bands[0].low, 0.0);
+    assert!((bands[63].high - 22050.0).abs() < 0.01);
+    assert!(bands[2].low < 1000.0 && 1000.0 < bands[2].high);
+  }
+
+  #[test]
+  fn perceptual_scales_are_monotonic_and_span_the_range() {
+    for scale in [
+      BandScale::Logarithmic,
+      BandScale::Mel,
+      BandScale::Bark,
+      BandScale::Erb,
+    ] {
+      let bands = layout(scale).bands(16, 48000);
+      assert_eq!(bands.len(), 16);
+      assert!((bands[0].low - 20.0).abs() < 0.1, "{:?}", scale);
+      assert!((bands[15].high - 20000.0).abs() < 2.0, "{:?}", scale);
+      for pair in bands.windows(2) {
+        assert!(
+          (pair[0].high - pair[1].low).abs() < pair[1].low * 1e-4,
+          "{:?}",
+          scale
+        );
+        assert!(pair[0].centre < pair[1].centre, "{:?}", scale);
+      }
+      // 低频部分比线性划分精细得多
+      assert!(bands[0].high < 200.0, "{:?}", scale);
+    }
+  }
+
+  #[test]
+  fn scales_round_trip() {
+    for scale in [BandScale::Mel, BandScale::Bark, BandScale::Erb] {
+      for frequency in [50.0, 1000.0, 15000.0] {
+        let back = from_scale(scale, to_scale(scale, frequency));
+        assert!((back - frequency).abs() / frequency < 1e-3, "{:?}", scale);
+      }
+    }
+  }
+
+  #[test]
+  fn octave_layouts_use_iso_centre_frequencies() {
+    let centres: Vec<f32> = layout(BandScale::Octave)
+      .bands(0, 48000)
+      .iter()
+      .map(|b| b.centre)
+      .collect();
+    assert_eq!(
+      centres,
+      vec![31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0]
+    );
+
+    let thirds = BandLayout {
+      scale: BandScale::ThirdOctave,
+      min_frequency: Some(790.0),
+      max_frequency: Some(3200.0),
+    }
+    .bands(0, 48000);
+    let centres: Vec<f32> = thirds.iter().map(|b| b.centre).collect();
+    assert_eq!(
+      centres,
+      vec![800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0]
+    );
+    assert!((thirds[1].low - 891.25).abs() < 0.1);
+    assert!((thirds[1].high - 1122.0).abs() < 0.1);
+  }
+
+  #[test]
+  fn invalid_ranges_do_not_panic() {
+    for (min, max) in [(0.0, -1.0), (f32::NAN, f32::NAN), (500.0, 100.0)] {
+      for scale in [BandScale::Linear, BandScale::Logarithmic, BandScale::Octave] {
+        // 无效范围只要求不 panic，参数校验在 `from_options` 中进行
+        BandLayout {
+          scale,
+          min_frequency: Some(min),
+          max_frequency: Some(max),
+        }
+        .bands(8, 48000);
+      }
+    }
+  }
+
+  fn options(scale: BandScale, min: Option<f64>, max: Option<f64>) -> BandLayoutOptions {
+    BandLayoutOptions {
+      scale,
+      min_frequency: min,
+      max_frequency: max,
+    }
+  }
+
+  #[test]
+  fn rejects_empty_band_ranges() {
+    for (options, message) in [
+      (
+        options(BandScale::Linear, Some(-1.0), None),
+        "minFrequency -1 must be a finite number of at least 0",
+      ),
+      (
+        options(BandScale::Linear, None, Some(f64::NAN)),
+        "maxFrequency NaN must be a finite number of at least 0",
+      ),
+      (
+        options(BandScale::Mel, Some(25000.0), None),
+        "Maximum frequency 20000 Hz must be above minimum frequency 25000 Hz",
+      ),
+      (
+        options(BandScale::Linear, Some(500.0), Some(100.0)),
+        "Maximum frequency 100 Hz must be above minimum frequency 500 Hz",
+      ),
+      (
+        options(BandScale::Logarithmic, None, Some(0.5)),
+        "Maximum frequency 0.5 Hz must be at least 1 Hz for logarithmic and octave layouts",
+      ),
+      (
+        options(BandScale::Logarithmic, Some(0.0), Some(1.0)),
+        "Maximum frequency 1 Hz must be above minimum frequency 1 Hz",
+      ),
+      (
+        options(BandScale::Octave, Some(0.0), Some(0.5)),
+        "Maximum frequency 0.5 Hz must be at least 1 Hz for logarithmic and octave layouts",
+      ),
+    ] {
+      assert_eq!(
+        BandLayout::from_options(options).unwrap_err().reason,
+        message
+      );
+    }
+
+    for options in [
+      options(BandScale::Linear, Some(15000.0), None),
+      options(BandScale::Logarithmic, Some(0.0), Some(2.0)),
+      options(BandScale::Octave, Some(0.0), Some(1.0)),
+    ] {
+      assert!(BandLayout::from_options(options).is_ok());
+    }
+  }
+}
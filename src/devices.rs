@@ -0,0 +1,95 @@
+use crate::types::DeviceKind;
+
+/// Endpoints the monitor can capture from, e.g. the devices known to Windows.
+pub trait DeviceList {
+  /// Ids of the active devices of `kind`: render endpoints for loopback, capture endpoints for input.
+  fn devices(&self, kind: DeviceKind) -> Vec<String>;
+
+  /// Id of the default device of `kind`, if there is one.
+  fn default_device(&self, kind: DeviceKind) -> Option<String>;
+}
+
+/// Device to capture: the requested one if it is active, the default device of `kind` otherwise,
+/// or the first active device when there is no default either.
+pub fn select_device(
+  list: &dyn DeviceList,
+  kind: DeviceKind,
+  requested: Option<&str>,
+) -> Option<String> {
+  let devices = list.devices(kind);
+  if let Some(id) = requested {
+    if devices.iter().any(|device| device == id) {
+      return Some(id.to_string());
+    }
+  }
+  list
+    .default_device(kind)
+    .or_else(|| devices.into_iter().next())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct FakeDevices {
+    outputs: Vec<&'static str>,
+    inputs: Vec<&'static str>,
+    default_input: Option<&'static str>,
+  }
+
+  impl DeviceList for FakeDevices {
+    fn devices(&self, kind: DeviceKind) -> Vec<String> {
+      let devices = match kind {
+        DeviceKind::Loopback => &self.outputs,
+        DeviceKind::Input => &self.inputs,
+      };
+      devices.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn default_device(&self, kind: DeviceKind) -> Option<String> {
+      match kind {
+        DeviceKind::Loopback => self.outputs.first().map(|id| id.to_string()),
+        DeviceKind::Input => self.default_input.map(str::to_string),
+      }
+    }
+  }
+
+  #[test]
+  fn selects_requested_device_of_the_same_kind() {
+    let devices = FakeDevices {
+      outputs: vec!["speakers", "headphones"],
+      inputs: vec!["microphone", "line-in"],
+      default_input: Some("microphone"),
+    };
+    let select = |kind, requested| select_device(&devices, kind, requested);
+
+    assert_eq!(
+      select(DeviceKind::Loopback, Some("headphones")).unwrap(),
+      "headphones"
+    );
+    assert_eq!(
+      select(DeviceKind::Input, Some("line-in")).unwrap(),
+      "line-in"
+    );
+    // 输出设备不能作为输入设备使用
+    assert_eq!(
+      select(DeviceKind::Input, Some("speakers")).unwrap(),
+      "microphone"
+    );
+    assert_eq!(select(DeviceKind::Loopback, None).unwrap(), "speakers");
+  }
+
+  #[test]
+  fn falls_back_to_the_first_device_without_default() {
+    let devices = FakeDevices {
+      outputs: vec![],
+      inputs: vec!["usb-microphone"],
+      default_input: None,
+    };
+    assert_eq!(
+      select_device(&devices, DeviceKind::Input, Some("unplugged")).unwrap(),
+      "usb-microphone"
+    );
+    assert_eq!(select_device(&devices, DeviceKind::Loopback, None), None);
+  }
+}
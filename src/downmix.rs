@@ -0,0 +1,78 @@
+use crate::types::DownmixMode;
+
+/// Combine de-interleaved channels into a single signal for the time domain modes, replacing
+/// the contents of `output` so that its buffer can be reused.
+/// [DownmixMode::Max] works on spectra and is handled by [max_spectrum]; here it falls back to mono.
+pub fn downmix(channels: &[Vec<f32>], mode: DownmixMode, output: &mut Vec<f32>) {
+  let len = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+  output.clear();
+  match (mode, channels) {
+    (_, []) => {}
+    (DownmixMode::Mid | DownmixMode::Mono | DownmixMode::Max, [only]) => {
+      output.extend_from_slice(only)
+    }
+    (DownmixMode::Side, [_]) => output.resize(len, 0.0),
+    (DownmixMode::Mid, [left, right, ..]) => {
+      output.extend((0..len).map(|i| (left[i] + right[i]) * 0.5))
+    }
+    (DownmixMode::Side, [left, right, ..]) => {
+      output.extend((0..len).map(|i| (left[i] - right[i]) * 0.5))
+    }
+    (DownmixMode::Mono | DownmixMode::Max, _) => {
+      let scale = 1.0 / channels.len() as f32;
+      output.extend((0..len).map(|i| channels.iter().map(|c| c[i]).sum::<f32>() * scale));
+    }
+  }
+}
+
+/// Raise every band of `acc` to the matching band of `spectrum`, both of equal length.
+pub fn max_spectrum(acc: &mut [f32], spectrum: &[f32]) {
+  for (a, &b) in acc.iter_mut().zip(spectrum) {
+    *a = a.max(b);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn mixed(channels: &[Vec<f32>], mode: DownmixMode) -> Vec<f32> {
+    let mut output = vec![9.0];
+    downmix(channels, mode, &mut output);
+    output
+  }
+
+  #[test]
+  fn stereo_modes() {
+    let channels = vec![vec![1.0, 0.5], vec![0.0, 0.5]];
+    assert_eq!(mixed(&channels, DownmixMode::Mono), vec![0.5, 0.5]);
+    assert_eq!(mixed(&channels, DownmixMode::Mid), vec![0.5, 0.5]);
+    assert_eq!(mixed(&channels, DownmixMode::Side), vec![0.5, 0.0]);
+  }
+
+  #[test]
+  fn mono_and_surround_inputs() {
+    let mono = vec![vec![0.25, -0.25]];
+    assert_eq!(mixed(&mono, DownmixMode::Mid), vec![0.25, -0.25]);
+    assert_eq!(mixed(&mono, DownmixMode::Side), vec![0.0, 0.0]);
+
+    let surround = vec![
+      vec![1.0],
+      vec![1.0],
+      vec![0.0],
+      vec![0.0],
+      vec![1.0],
+      vec![1.0],
+    ];
+    assert_eq!(mixed(&surround, DownmixMode::Mono), vec![4.0 / 6.0]);
+    assert_eq!(mixed(&surround, DownmixMode::Mid), vec![1.0]);
+    assert!(mixed(&[], DownmixMode::Mono).is_empty());
+  }
+
+  #[test]
+  fn max_of_spectra() {
+    let mut acc = vec![0.1, 0.9, 0.3];
+    max_spectrum(&mut acc, &[0.5, 0.2, 0.3]);
+    assert_eq!(acc, vec![0.5, 0.9, 0.3]);
+  }
+}
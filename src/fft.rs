@@ -1,43 +1,488 @@
+use std::sync::Arc;
+
 use num_complex::Complex;
-use rustfft::FftPlanner;
+use rustfft::{Fft, FftPlanner};
+
+use crate::bands::{Band, BandLayout};
+use crate::types::{FrequencyWeighting, SpectrumScale};
+use crate::weighting::weighting_gain;
+use crate::window::{coherent_gain, noise_bandwidth, Window};
+
+/// Default lower end of the decibel scales, in dBFS.
+pub const DEFAULT_FLOOR: f32 = -90.0;
+/// Default upper end of the normalised scale, in dBFS.
+pub const DEFAULT_CEILING: f32 = 0.0;
+
+/// Spectrum analyser owning the FFT plan, window table and every working buffer,
+/// so that repeated calls with the same sizes do not allocate.
+pub struct SpectrumAnalyzer {
+  planner: FftPlanner<f32>,
+  fft: Arc<dyn Fft<f32>>,
+  window_function: Window,
+  window: Vec<f32>,
+  window_gain: f32,
+  window_bandwidth: f32,
+  buffer: Vec<Complex<f32>>,
+  scratch: Vec<Complex<f32>>,
+  magnitudes: Vec<f32>,
+  powers: Vec<f32>,
+  weighting: FrequencyWeighting,
+  // 每个频点的计权增益，FFT 点数或采样率变化时重新计算
+  weights: Vec<f32>,
+  level: f32,
+  scale: SpectrumScale,
+  floor: f32,
+  ceiling: f32,
+  sample_rate: u32,
+  layout: BandLayout,
+  // 当前频段划分及其对应的频段数，参数变化时才重新计算
+  band_ranges: Vec<Band>,
+  band_key: Option<(usize, u32)>,
+  bands: Vec<f32>,
+}
+
+impl SpectrumAnalyzer {
+  /// Create an analyser for blocks of `size` samples, rounded up to a power of two.
+  pub fn new(size: usize) -> Self {
+    let mut planner = FftPlanner::new();
+    let fft = planner.plan_fft_forward(1);
+    let mut analyzer = SpectrumAnalyzer {
+      planner,
+      fft,
+      window_function: Window::Hann,
+      window: Vec::new(),
+      window_gain: 1.0,
+      window_bandwidth: 1.0,
+      buffer: Vec::new(),
+      scratch: Vec::new(),
+      magnitudes: Vec::new(),
+      powers: Vec::new(),
+      weighting: FrequencyWeighting::Z,
+      weights: Vec::new(),
+      level: DEFAULT_FLOOR,
+      scale: SpectrumScale::Log,
+      floor: DEFAULT_FLOOR,
+      ceiling: DEFAULT_CEILING,
+      sample_rate: 48000,
+      layout: BandLayout::default(),
+      band_ranges: Vec::new(),
+      band_key: None,
+      bands: Vec::new(),
+    };
+    analyzer.resize(size);
+    analyzer
+  }
+
+  pub fn fft_size(&self) -> usize {
+    self.window.len()
+  }
+
+  /// Re-plan for blocks of `size` samples. Does nothing if the FFT size is unchanged.
+  pub fn resize(&mut self, size: usize) {
+    let fft_size = size.max(1).next_power_of_two();
+    if fft_size == self.fft_size() {
+      return;
+    }
+
+    self.fft = self.planner.plan_fft_forward(fft_size);
+    self.window = self.window_function.coefficients(fft_size);
+    self.window_gain = coherent_gain(&self.window);
+    self.window_bandwidth = noise_bandwidth(&self.window);
+    self.buffer = vec![Complex::new(0.0, 0.0); fft_size];
+    self.scratch = vec![Complex::new(0.0, 0.0); self.fft.get_inplace_scratch_len()];
+    self.magnitudes = vec![0.0; fft_size / 2];
+    self.powers = vec![0.0; fft_size / 2];
+    self.update_weights();
+  }
+
+  /// Switch the window function, rebuilding the window table if it changed.
+  pub fn set_window(&mut self, window: Window) {
+    if window == self.window_function {
+      return;
+    }
+    self.window_function = window;
+    self.window = window.coefficients(self.fft_size());
+    self.window_gain = coherent_gain(&self.window);
+    self.window_bandwidth = noise_bandwidth(&self.window);
+  }
+
+  /// Choose the unit of the band values. `floor` and `ceiling` are in dBFS.
+  pub fn set_scale(&mut self, scale: SpectrumScale, floor: f32, ceiling: f32) {
+    self.scale = scale;
+    self.floor = floor;
+    self.ceiling = ceiling;
+  }
+
+  /// Frequency weighting applied to the magnitudes and the level.
+  pub fn set_weighting(&mut self, weighting: FrequencyWeighting) {
+    if weighting != self.weighting {
+      self.weighting = weighting;
+      self.update_weights();
+    }
+  }
+
+  /// Lowest value [SpectrumAnalyzer::analyze] produces in the current scale.
+  pub fn floor(&self) -> f32 {
+    match self.scale {
+      SpectrumScale::Decibels => self.floor,
+      _ => 0.0,
+    }
+  }
+
+  /// Sample rate of the analysed signal, used to map bins to frequencies.
+  pub fn set_sample_rate(&mut self, sample_rate: u32) {
+    if sample_rate != self.sample_rate {
+      self.sample_rate = sample_rate.max(1);
+      self.band_key = None;
+      self.update_weights();
+    }
+  }
+
+  pub fn set_layout(&mut self, layout: BandLayout) {
+    if layout != self.layout {
+      self.layout = layout;
+      self.band_key = None;
+    }
+  }
+
+  /// Frequency ranges of the bands produced for `num_bands`.
+  pub fn band_ranges(&mut self, num_bands: usize) -> &[Band] {
+    if self.band_key != Some((num_bands, self.sample_rate)) {
+      self.band_ranges = self.layout.bands(num_bands, self.sample_rate);
+      self.band_key = Some((num_bands, self.sample_rate));
+    }
+    &self.band_ranges
+  }
+
+  /// Magnitude of every FFT bin up to Nyquist from the last [SpectrumAnalyzer::analyze] call,
+  /// compensated for the coherent gain of the window and weighted.
+  #[cfg(test)]
+  pub fn magnitudes(&self) -> &[f32] {
+    &self.magnitudes
+  }
+
+  /// Weighted level of `samples` in dBFS, where a full scale sine reads 0 dB. Never below the floor.
+  pub fn level(&mut self, samples: &[f32]) -> f32 {
+    self.transform(samples);
+    self.level
+  }
+
+  /// Compute the band levels of `samples`, grouped according to the band layout
+  /// and expressed in the configured scale.
+  ///
+  /// The returned slice is owned by the analyser and overwritten by the next call.
+  pub fn analyze(&mut self, samples: &[f32], num_bands: usize) -> &[f32] {
+    self.transform(samples);
+    let fft_size = self.fft_size();
+
+    self.band_ranges(num_bands);
+    self.bands.resize(self.band_ranges.len(), 0.0);
+    // 频点 k 代表 [k, k + 1) 倍频点宽度的频率范围，与等宽划分时的分组方式一致
+    let bins_per_hz = fft_size as f32 / self.sample_rate as f32;
+
+    if self.scale == SpectrumScale::Log {
+      for (band, range) in self.bands.iter_mut().zip(self.band_ranges.iter()) {
+        let (sum, weight) = band_sum(
+          &self.magnitudes,
+          range.low * bins_per_hz - 0.5,
+          range.high * bins_per_hz - 0.5,
+        );
+        // 原有刻度不补偿窗函数的相干增益，与之前的数值保持一致
+        let level = if weight > 0.0 { sum / weight } else { 0.0 };
+        *band = (1.0 + level * self.window_gain).log10();
+      }
+      return &self.bands;
+    }
+
+    // 归一化到满幅正弦波为 1.0，与 FFT 点数无关
+    let norm = 2.0 / fft_size as f32;
+    for (power, magnitude) in self.powers.iter_mut().zip(self.magnitudes.iter()) {
+      *power = (magnitude * norm) * (magnitude * norm);
+    }
+
+    for (band, range) in self.bands.iter_mut().zip(self.band_ranges.iter()) {
+      // 频段内的总功率，除以窗函数的等效噪声带宽后，单音与宽带信号的电平都与 FFT 点数无关
+      let (power, _) = band_sum(
+        &self.powers,
+        range.low * bins_per_hz - 0.5,
+        range.high * bins_per_hz - 0.5,
+      );
+      let db = (10.0 * (power / self.window_bandwidth).log10()).max(self.floor);
+      *band = match self.scale {
+        SpectrumScale::Normalized => ((db - self.floor) / (self.ceiling - self.floor)).min(1.0),
+        _ => db,
+      };
+    }
+
+    &self.bands
+  }
+
+  // 加窗并计算计权后的幅度谱与总电平
+  fn transform(&mut self, samples: &[f32]) {
+    self.resize(samples.len());
+    let fft_size = self.fft_size();
+
+    // 加窗，不足部分补零
+    for (i, value) in self.buffer.iter_mut().enumerate() {
+      let sample = samples.get(i).copied().unwrap_or(0.0);
+      *value = Complex::new(sample * self.window[i], 0.0);
+    }
 
-pub fn analyze_spectrum(samples: &[f32], num_bands: usize) -> Vec<f32> {
-  let fft_size = samples.len().next_power_of_two();
-  let mut fft_input: Vec<Complex<f32>> = samples
-    .iter()
-    .take(fft_size)
-    .map(|&s| Complex::new(s, 0.0))
-    .collect();
+    self
+      .fft
+      .process_with_scratch(&mut self.buffer, &mut self.scratch);
 
-  for i in 0..fft_size {
-    let window =
-      0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (fft_size as f32 - 1.0)).cos());
-    fft_input[i] = fft_input[i] * window;
+    // 补偿窗函数的相干增益，使不同窗函数下的电平一致
+    let gain = 1.0 / self.window_gain;
+    let norm = 2.0 / fft_size as f32;
+    let mut total = 0.0;
+    for ((magnitude, value), weight) in self
+      .magnitudes
+      .iter_mut()
+      .zip(self.buffer.iter())
+      .zip(self.weights.iter())
+    {
+      *magnitude = value.norm() * gain * weight;
+      total += (*magnitude * norm) * (*magnitude * norm);
+    }
+    self.level = (10.0 * (total / self.window_bandwidth).log10()).max(self.floor);
   }
 
-  fft_input.resize(fft_size, Complex::new(0.0, 0.0));
+  fn update_weights(&mut self) {
+    let bin_width = self.sample_rate as f32 / self.fft_size() as f32;
+    self.weights.clear();
+    self.weights.extend(
+      (0..self.fft_size() / 2).map(|k| weighting_gain(self.weighting, k as f32 * bin_width)),
+    );
+  }
+}
+
+// 频段 [start, end)（以频点为单位）内的加权和与总权重。频点 k 覆盖 [k - 0.5, k + 0.5)，
+// 频段边缘处按重叠比例加权；比一个频点还窄的频段在相邻频点间线性插值，权重为 1
+fn band_sum(values: &[f32], start: f32, end: f32) -> (f32, f32) {
+  let last = match values.len() {
+    0 => return (0.0, 0.0),
+    len => len - 1,
+  };
+
+  if end - start < 1.0 {
+    let centre = ((start + end) / 2.0).clamp(0.0, last as f32);
+    let index = centre.floor() as usize;
+    let next = (index + 1).min(last);
+    let frac = centre - index as f32;
+    return (values[index] * (1.0 - frac) + values[next] * frac, 1.0);
+  }
+
+  let first = (start + 0.5).floor().max(0.0) as usize;
+  let end_bin = ((end + 0.5).ceil().max(0.0) as usize).min(last + 1);
+  let mut sum = 0.0;
+  let mut weight = 0.0;
+  for (k, value) in values.iter().enumerate().take(end_bin).skip(first) {
+    let overlap = (end.min(k as f32 + 0.5) - start.max(k as f32 - 0.5)).max(0.0);
+    sum += value * overlap;
+    weight += overlap;
+  }
+
+  (sum, weight)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::source::{Generator, Signal};
+  use crate::test_alloc::count_allocations;
+  use crate::types::BandScale;
 
-  let mut planner = FftPlanner::new();
-  let fft = planner.plan_fft_forward(fft_size);
-  let mut fft_output = fft_input.clone();
-  fft.process(&mut fft_output);
+  fn tone(frequency: f32, len: usize) -> Vec<f32> {
+    let mut generator = Generator::new(Signal::Sine { frequency }, 44100, 0.5);
+    let mut samples = vec![0.0; len];
+    generator.fill(&mut samples);
+    samples
+  }
+
+  fn peak_band(spectrum: &[f32]) -> usize {
+    (0..spectrum.len())
+      .max_by(|&a, &b| spectrum[a].total_cmp(&spectrum[b]))
+      .unwrap()
+  }
+
+  #[test]
+  fn tone_lights_up_expected_band() {
+    let mut analyzer = SpectrumAnalyzer::new(2048);
+    // 2048 点 FFT，每个频段 16 个频点，约 344 Hz
+    let spectrum = analyzer.analyze(&tone(1000.0, 2048), 64);
+    assert_eq!(peak_band(spectrum), 2);
+
+    let spectrum = analyzer.analyze(&tone(5000.0, 2048), 64);
+    assert_eq!(peak_band(spectrum), 14);
+  }
 
-  let magnitudes: Vec<f32> = fft_output
-    .iter()
-    .take(fft_size / 2)
-    .map(|c| c.norm())
-    .collect();
+  #[test]
+  fn replans_when_the_block_size_changes() {
+    let mut analyzer = SpectrumAnalyzer::new(2048);
+    let spectrum = analyzer.analyze(&tone(1000.0, 1000), 32);
+    assert_eq!(peak_band(spectrum), 1);
+    assert_eq!(analyzer.fft_size(), 1024);
+  }
 
-  let mut spectrum = vec![0.0f32; num_bands];
-  let bins_per_band = (fft_size / 2) / num_bands;
+  #[test]
+  fn log_scale_keeps_the_original_values() {
+    // 频率正好落在第 32 个频点上，Hann 窗下幅度为 0.5 * 1024 / 4
+    let mut analyzer = SpectrumAnalyzer::new(1024);
+    let spectrum = analyzer.analyze(&tone(44100.0 / 1024.0 * 32.0, 1024), 512);
+    let expected = 129f32.log10();
+    assert!((spectrum[32] - expected).abs() < 1e-3, "{}", spectrum[32]);
+  }
+
+  #[test]
+  fn windows_keep_the_tone_level() {
+    // 频率正好落在第 46 个频点上
+    let samples = tone(44100.0 / 2048.0 * 46.0, 2048);
+    let mut analyzer = SpectrumAnalyzer::new(2048);
+    let windows = [
+      Window::Rectangular,
+      Window::Hann,
+      Window::Hamming,
+      Window::Blackman,
+      Window::BlackmanHarris,
+      Window::FlatTop,
+      Window::Kaiser { beta: 8.6 },
+      Window::Gaussian { sigma: 0.4 },
+    ];
+    for window in windows {
+      analyzer.set_window(window);
+      analyzer.analyze(&samples, 64);
+      // 0.5 振幅的正弦，峰值频点幅度约为 0.5 * N / 2
+      let peak = analyzer.magnitudes()[46];
+      assert!(
+        (peak - 512.0).abs() < 512.0 * 0.01,
+        "{:?}: {}",
+        window,
+        peak
+      );
+    }
+  }
 
-  for i in 0..num_bands {
-    let start = i * bins_per_band;
-    let end = (i + 1) * bins_per_band;
+  #[test]
+  fn log_layout_resolves_low_frequencies() {
+    let mut analyzer = SpectrumAnalyzer::new(2048);
+    analyzer.set_sample_rate(44100);
+    analyzer.set_layout(BandLayout {
+      scale: BandScale::Logarithmic,
+      min_frequency: Some(20.0),
+      max_frequency: Some(20000.0),
+    });
 
-    spectrum[i] = magnitudes[start..end].iter().sum::<f32>() / bins_per_band as f32;
-    spectrum[i] = (1.0 + spectrum[i]).log10();
+    let low = peak_band(analyzer.analyze(&tone(100.0, 2048), 16));
+    let high = peak_band(analyzer.analyze(&tone(1000.0, 2048), 16));
+    // 20 Hz ~ 20 kHz 分为 16 段，每段约 0.62 个倍频程
+    assert_eq!(low, 3);
+    assert_eq!(high, 9);
   }
 
-  spectrum
+  #[test]
+  fn narrow_bands_are_interpolated() {
+    let magnitudes = [0.0, 1.0, 3.0, 1.0];
+    assert_eq!(band_sum(&magnitudes, 1.25, 1.75), (2.0, 1.0));
+    assert_eq!(band_sum(&magnitudes, 0.5, 2.5), (4.0, 2.0));
+    assert_eq!(band_sum(&magnitudes, -0.5, 1.5), (1.0, 2.0));
+    assert_eq!(band_sum(&magnitudes, 0.0, 1.0), (0.5, 1.0));
+
+    let mut analyzer = SpectrumAnalyzer::new(2048);
+    analyzer.set_sample_rate(44100);
+    analyzer.set_layout(BandLayout {
+      scale: BandScale::ThirdOctave,
+      min_frequency: Some(20.0),
+      max_frequency: None,
+    });
+    let spectrum = analyzer.analyze(&tone(1000.0, 2048), 0);
+    assert_eq!(spectrum.len(), 30);
+    assert!(spectrum.iter().all(|v| v.is_finite()));
+    let peak = peak_band(spectrum);
+    assert_eq!(analyzer.band_ranges(0)[peak].centre, 1000.0);
+  }
+
+  #[test]
+  fn decibels_do_not_depend_on_fft_size() {
+    for size in [512, 2048, 8192] {
+      let mut analyzer = SpectrumAnalyzer::new(size);
+      analyzer.set_sample_rate(44100);
+      analyzer.set_scale(SpectrumScale::Decibels, DEFAULT_FLOOR, DEFAULT_CEILING);
+
+      // 0.5 振幅的正弦约为 -6 dBFS
+      let spectrum = analyzer.analyze(&tone(1000.0, size), 8);
+      assert!(
+        (spectrum[0] + 6.02).abs() < 0.1,
+        "{}: {}",
+        size,
+        spectrum[0]
+      );
+      assert!(spectrum[4] < -60.0, "{}: {}", size, spectrum[4]);
+    }
+  }
+
+  #[test]
+  fn weighting_applies_to_bands_and_level() {
+    let mut analyzer = SpectrumAnalyzer::new(8192);
+    analyzer.set_sample_rate(44100);
+    analyzer.set_scale(SpectrumScale::Decibels, DEFAULT_FLOOR, DEFAULT_CEILING);
+    analyzer.set_layout(BandLayout {
+      scale: BandScale::Octave,
+      min_frequency: None,
+      max_frequency: None,
+    });
+
+    let low = tone(100.0, 8192);
+    let flat = analyzer.analyze(&low, 0).to_vec();
+    let flat_level = analyzer.level(&low);
+    assert!((flat_level + 6.02).abs() < 0.2, "{}", flat_level);
+
+    analyzer.set_weighting(FrequencyWeighting::A);
+    let weighted = analyzer.analyze(&low, 0).to_vec();
+    // 125 Hz 倍频程，A 计权在 100 Hz 约为 -19.1 dB
+    let attenuation = flat[2] - weighted[2];
+    assert!((attenuation - 19.1).abs() < 0.3, "{}", attenuation);
+    let level = analyzer.level(&low);
+    assert!((flat_level - level - 19.1).abs() < 0.3, "{}", level);
+
+    // 1 kHz 不受影响
+    let level = analyzer.level(&tone(1000.0, 8192));
+    assert!((level + 6.02).abs() < 0.2, "{}", level);
+  }
+
+  #[test]
+  fn normalized_maps_floor_and_ceiling() {
+    let mut analyzer = SpectrumAnalyzer::new(2048);
+    analyzer.set_sample_rate(44100);
+    analyzer.set_scale(SpectrumScale::Normalized, -60.0, 0.0);
+
+    let spectrum = analyzer.analyze(&tone(1000.0, 2048), 8).to_vec();
+    assert!((spectrum[0] - 0.9).abs() < 0.01, "{}", spectrum[0]);
+    assert!(spectrum.iter().all(|v| (0.0..=1.0).contains(v)));
+
+    let silence = analyzer.analyze(&[0.0; 2048], 8);
+    assert!(silence.iter().all(|&v| v == 0.0));
+  }
+
+  #[test]
+  fn silence_is_flat() {
+    let spectrum = SpectrumAnalyzer::new(2048)
+      .analyze(&[0.0; 2048], 16)
+      .to_vec();
+    assert!(spectrum.iter().all(|&v| v == 0.0));
+  }
+
+  #[test]
+  fn steady_state_does_not_allocate() {
+    let samples = tone(1000.0, 2048);
+    let mut analyzer = SpectrumAnalyzer::new(2048);
+    analyzer.analyze(&samples, 64);
+
+    let allocated = count_allocations(|| {
+      for _ in 0..1000 {
+        analyzer.analyze(&samples, 64);
+      }
+    });
+    assert_eq!(allocated, 0);
+  }
 }
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use crate::source::CaptureSource;
+use crate::types::{DeviceDirection, DeviceRole};
+use crate::watcher::{DeviceNotification, DeviceNotifier};
+
+/// Creates the capture source of a device when the monitor switches to it.
+pub type SourceFactory = Box<dyn Fn(&str) -> Box<dyn CaptureSource> + Send>;
+
+/// Decides when a monitor that follows the default device has to switch its capture source.
+pub struct DeviceFollower {
+  notifier: Box<dyn DeviceNotifier>,
+  direction: DeviceDirection,
+  factory: SourceFactory,
+  current: Option<String>,
+}
+
+/// A switch from the previously captured device to the new default device.
+#[derive(Debug, PartialEq)]
+pub struct DeviceSwitch {
+  pub previous_id: Option<String>,
+  pub device_id: String,
+}
+
+impl DeviceFollower {
+  /// Follow the default device of `direction` as reported by `notifier`, e.g. the receiver of
+  /// [DeviceWatcher::default_changes](crate::watcher::DeviceWatcher::default_changes),
+  /// starting from the device `current`.
+  pub fn new(
+    notifier: Box<dyn DeviceNotifier>,
+    direction: DeviceDirection,
+    current: Option<String>,
+    factory: SourceFactory,
+  ) -> Self {
+    DeviceFollower {
+      notifier,
+      direction,
+      factory,
+      current,
+    }
+  }
+
+  /// Device to switch to, if the default changed to a device other than the captured one.
+  /// Does not block.
+  pub fn poll(&mut self) -> Option<DeviceSwitch> {
+    // 只跟随控制台角色的默认设备，即未指定设备时打开的设备
+    let device_id = loop {
+      if let DeviceNotification::DefaultChanged(direction, DeviceRole::Console, Some(id)) =
+        self.notifier.next(Duration::ZERO)?
+      {
+        if direction == self.direction {
+          break id;
+        }
+      }
+    };
+    // 同一设备的重复通知（例如不同角色）无需切换
+    if self.current.as_ref() == Some(&device_id) {
+      return None;
+    }
+    let previous_id = self.current.replace(device_id.clone());
+    Some(DeviceSwitch {
+      previous_id,
+      device_id,
+    })
+  }
+
+  /// Record that `device_id` is being captured, e.g. after reconnecting to a fallback device.
+  pub fn set_current(&mut self, device_id: String) {
+    self.current = Some(device_id);
+  }
+
+  /// Capture source of the device with the given id.
+  pub fn source(&self, device_id: &str) -> Box<dyn CaptureSource> {
+    (self.factory)(device_id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::source::{Generator, GeneratorSource, Signal};
+  use crate::watcher::ScriptedNotifier;
+
+  fn default(direction: DeviceDirection, role: DeviceRole, id: &str) -> DeviceNotification {
+    DeviceNotification::DefaultChanged(direction, role, Some(id.to_string()))
+  }
+
+  fn follower(script: Vec<DeviceNotification>) -> DeviceFollower {
+    DeviceFollower::new(
+      Box::new(ScriptedNotifier(script.into())),
+      DeviceDirection::Render,
+      Some("speakers".to_string()),
+      Box::new(|_| {
+        let generator = Generator::new(Signal::Silence, 48000, 0.5);
+        Box::new(GeneratorSource::new(generator, 2, false, None))
+      }),
+    )
+  }
+
+  #[test]
+  fn switches_only_when_the_default_moves_to_another_device() {
+    use DeviceDirection::{Capture, Render};
+    use DeviceRole::{Communications, Console};
+    let mut follower = follower(vec![
+      default(Render, Console, "speakers"),
+      // 其他方向和角色的默认设备不影响跟随
+      default(Capture, Console, "microphone"),
+      default(Render, Communications, "headset"),
+      default(Render, Console, "headphones"),
+      default(Render, Console, "headphones"),
+      default(Render, Console, "speakers"),
+    ]);
+
+    assert_eq!(follower.poll(), None);
+    assert_eq!(
+      follower.poll(),
+      Some(DeviceSwitch {
+        previous_id: Some("speakers".to_string()),
+        device_id: "headphones".to_string(),
+      })
+    );
+    assert_eq!(follower.poll(), None);
+    assert_eq!(follower.poll().unwrap().device_id, "speakers");
+    assert_eq!(follower.poll(), None);
+  }
+}
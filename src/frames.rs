@@ -0,0 +1,106 @@
+/// Sliding window over the most recent `size` samples of every channel, so that analysis
+/// frames can overlap and be produced more often than once per FFT length.
+pub struct SlidingFrames {
+  size: usize,
+  hop: usize,
+  // 每个通道的环形缓冲区，`position` 为下一个写入位置
+  rings: Vec<Vec<f32>>,
+  position: usize,
+  filled: usize,
+  pending: usize,
+}
+
+impl SlidingFrames {
+  /// Frames of `size` samples, one every `hop` samples. `hop` is clamped to 1..=size.
+  pub fn new(size: usize, hop: usize) -> Self {
+    let size = size.max(1);
+    SlidingFrames {
+      size,
+      hop: hop.clamp(1, size),
+      rings: Vec::new(),
+      position: 0,
+      filled: 0,
+      pending: 0,
+    }
+  }
+
+  pub fn size(&self) -> usize {
+    self.size
+  }
+
+  pub fn hop(&self) -> usize {
+    self.hop
+  }
+
+  /// Append per-channel samples and return the latest frame if `hop` samples arrived since
+  /// the previous one and the window has been filled once.
+  pub fn push(&mut self, samples: &[Vec<f32>]) -> Option<Vec<Vec<f32>>> {
+    // 通道数变化时重新开始填充
+    if self.rings.len() != samples.len() {
+      self.rings = vec![vec![0.0; self.size]; samples.len()];
+      self.position = 0;
+      self.filled = 0;
+      self.pending = 0;
+    }
+
+    let count = samples.first().map_or(0, |channel| channel.len());
+    for i in 0..count {
+      for (ring, channel) in self.rings.iter_mut().zip(samples) {
+        ring[self.position] = channel[i];
+      }
+      self.position = (self.position + 1) % self.size;
+    }
+    self.filled = (self.filled + count).min(self.size);
+    self.pending += count;
+
+    if self.filled < self.size || self.pending < self.hop {
+      return None;
+    }
+    self.pending = 0;
+    Some(self.frame())
+  }
+
+  // 按时间顺序取出整个窗口
+  fn frame(&self) -> Vec<Vec<f32>> {
+    self
+      .rings
+      .iter()
+      .map(|ring| {
+        let mut frame = Vec::with_capacity(self.size);
+        frame.extend_from_slice(&ring[self.position..]);
+        frame.extend_from_slice(&ring[..self.position]);
+        frame
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ramp(start: usize, len: usize) -> Vec<f32> {
+    (start..start + len).map(|v| v as f32).collect()
+  }
+
+  #[test]
+  fn frames_overlap_by_size_minus_hop() {
+    let mut frames = SlidingFrames::new(8, 2);
+    assert_eq!(frames.push(&[ramp(0, 6), ramp(100, 6)]), None);
+
+    let frame = frames.push(&[ramp(6, 2), ramp(106, 2)]).unwrap();
+    assert_eq!(frame, vec![ramp(0, 8), ramp(100, 8)]);
+
+    assert_eq!(frames.push(&[ramp(8, 1), ramp(108, 1)]), None);
+    let frame = frames.push(&[ramp(9, 1), ramp(109, 1)]).unwrap();
+    assert_eq!(frame, vec![ramp(2, 8), ramp(102, 8)]);
+  }
+
+  #[test]
+  fn hop_equal_to_size_does_not_overlap() {
+    let mut frames = SlidingFrames::new(4, 4);
+    assert_eq!(frames.push(&[ramp(0, 4)]).unwrap(), vec![ramp(0, 4)]);
+    assert_eq!(frames.push(&[ramp(4, 4)]).unwrap(), vec![ramp(4, 4)]);
+    assert_eq!(SlidingFrames::new(4, 16).hop(), 4);
+  }
+}
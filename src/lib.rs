@@ -3,11 +3,51 @@
 // #[macro_use]
 extern crate napi_derive;
 
+mod agc;
+mod backoff;
+mod bands;
+mod devices;
+mod downmix;
 mod fft;
+mod follow;
+mod frames;
 mod monitor;
+mod pipeline;
+mod reader;
+mod smoothing;
+mod source;
+#[cfg(test)]
+mod test_alloc;
 mod types;
 mod utils;
+#[cfg(windows)]
 mod wasapi;
+mod watcher;
+mod weighting;
+mod window;
 
-pub use crate::types::AudioDevice;
-pub use crate::utils::{get_all_output_devices, get_default_output_device};
+pub use crate::devices::{select_device, DeviceList};
+pub use crate::follow::{DeviceFollower, DeviceSwitch, SourceFactory};
+pub use crate::monitor::AudioMonitor;
+pub use crate::reader::FrameReader;
+pub use crate::source::{
+  CaptureError, CaptureSource, Generator, GeneratorSource, PcmDecoder, SampleType, Signal,
+  StreamFormat, WavSource,
+};
+pub use crate::types::{
+  AudioDevice, AutoGainOptions, BandLayoutOptions, BandScale, CaptureFormat, DefaultDeviceEvent,
+  DefaultRoles, DeviceDirection, DeviceEvent, DeviceKind, DeviceListOptions, DeviceOptions,
+  DeviceRole, DeviceState, DeviceSwitchEvent, DownmixMode, ErrorCode, FileSourceOptions,
+  FrameOptions, FrameOverflow, FrameResult, FrameStreamOptions, FrequencyWeighting, GeneratorOptions,
+  MonitorError, ProcessOptions, ReconnectEvent, ReconnectOptions, ScaleOptions, SignalKind,
+  SmoothingOptions, SpectrumCallbackOptions, SpectrumEvent, SpectrumFrame, SpectrumScale,
+  StreamInfo, WindowFunction,
+};
+#[cfg(windows)]
+pub use crate::utils::{
+  get_all_input_devices, get_all_output_devices, get_default_input_device,
+  get_default_output_device,
+};
+pub use crate::watcher::{
+  DeviceChange, DeviceNotification, DeviceNotifier, DeviceTracker, DeviceWatcher,
+};
@@ -1,23 +1,90 @@
+use std::any::Any;
 use std::collections::VecDeque;
+use std::mem;
 use std::sync::mpsc::{self, Receiver, SyncSender};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::fft;
-use crate::utils::{extract_float_samples, get_output_device_by_id};
-use crate::wasapi::*;
-use log::{debug, error, info};
-use napi::{Error, Result, Status};
+use crate::agc::AutoGain;
+use crate::backoff::Backoff;
+use crate::bands::BandLayout;
+use crate::fft::{DEFAULT_CEILING, DEFAULT_FLOOR};
+use crate::follow::{DeviceFollower, DeviceSwitch};
+use crate::frames::SlidingFrames;
+use crate::pipeline::{BandState, SpectrumPipeline};
+use crate::reader::{FrameQueue, FrameReader, FrameSender};
+use crate::smoothing::BandSmoother;
+use crate::source::{
+  CaptureError, CaptureRes, CaptureSource, GeneratorConfig, PcmDecoder, StreamFormat, WavSource,
+};
+#[cfg(windows)]
+use crate::source::{DeviceSource, ProcessSource};
+use crate::types::{
+  AutoGainOptions, BandLayoutOptions, BandScale, CaptureFormat, DeviceKind, DeviceOptions,
+  DeviceSwitchEvent, DownmixMode, FileSourceOptions, FrameOptions, FrameOverflow,
+  FrameStreamOptions, FrequencyWeighting, GeneratorOptions, MonitorError, ProcessOptions,
+  ReconnectEvent, ReconnectOptions, ScaleOptions, SmoothingOptions, SpectrumCallbackOptions,
+  SpectrumEvent, SpectrumFrame, SpectrumScale, StreamInfo, WindowFunction,
+};
+use crate::utils::{check_value, extract_float_samples, set_locked};
+#[cfg(windows)]
+use crate::watcher::DeviceWatcher;
+use crate::window::Window;
+use log::{debug, error, info, warn};
+use napi::bindgen_prelude::Float32Array;
+use napi::threadsafe_function::{
+  ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Env, Error, JsFunction, JsObject, Result, Status};
 use napi_derive::napi;
 
-type AudioData = Vec<f32>;
+// 每个通道一组样本
+type AudioData = Vec<Vec<f32>>;
+
+// `onError` 注册的回调
+type ErrorCallback = Box<dyn Fn(MonitorError) + Send>;
+
+// `onReconnect` 注册的回调
+type ReconnectCallback = Box<dyn Fn(ReconnectEvent) + Send>;
+
+// `onDeviceSwitch` 注册的回调
+type SwitchCallback = Box<dyn Fn(DeviceSwitchEvent) + Send>;
+
+// 监控器使用的数据源
+enum SourceConfig {
+  Device,
+  Process { process_id: u32, include_tree: bool },
+  File { path: String, realtime: bool },
+  Generator(GeneratorConfig),
+}
 
 #[napi(js_name = "AudioMonitor")]
 pub struct AudioMonitor {
   chunk_size: usize,
+  hop_size: Option<usize>,
+  kind: DeviceKind,
   device_id: Option<String>,
+  source: SourceConfig,
+  capture_format: Option<StreamFormat>,
+  window: WindowFunction,
+  band_scale: BandScale,
+  scale: SpectrumScale,
+  weighting: FrequencyWeighting,
+  format: Arc<Mutex<Option<StreamFormat>>>,
   spectrum: Arc<Mutex<AudioData>>,
+  pipeline: Arc<Mutex<SpectrumPipeline>>,
+  callback: Arc<Mutex<Option<SpectrumCallback>>>,
+  readers: Arc<Mutex<Vec<SpectrumCallback>>>,
+  last_error: Arc<Mutex<Option<MonitorError>>>,
+  error_callback: Arc<Mutex<Option<ErrorCallback>>>,
+  reconnect: Arc<Mutex<Option<Backoff>>>,
+  reconnect_callback: Arc<Mutex<Option<ReconnectCallback>>>,
+  current_device: Arc<Mutex<Option<String>>>,
+  switch_callback: Arc<Mutex<Option<SwitchCallback>>>,
+  // 跟随默认设备时提供默认设备变化的通知
+  #[cfg(windows)]
+  device_watcher: Option<DeviceWatcher>,
   running: Arc<Mutex<bool>>,
   worker_handle: Option<JoinHandle<()>>,
 }
@@ -28,16 +95,45 @@ impl AudioMonitor {
   pub fn new() -> Self {
     AudioMonitor {
       chunk_size: 2048, // 默认值
+      hop_size: None,
+      kind: DeviceKind::Loopback,
       device_id: None,
+      source: SourceConfig::Device,
+      capture_format: None,
+      window: WindowFunction::Hann,
+      band_scale: BandScale::Linear,
+      scale: SpectrumScale::Log,
+      weighting: FrequencyWeighting::Z,
+      format: Arc::new(Mutex::new(None)),
       spectrum: Arc::new(Mutex::new(Vec::new())),
+      pipeline: Arc::new(Mutex::new(SpectrumPipeline::new(2048))),
+      callback: Arc::new(Mutex::new(None)),
+      readers: Arc::new(Mutex::new(Vec::new())),
+      last_error: Arc::new(Mutex::new(None)),
+      error_callback: Arc::new(Mutex::new(None)),
+      reconnect: Arc::new(Mutex::new(Some(Backoff::default()))),
+      reconnect_callback: Arc::new(Mutex::new(None)),
+      current_device: Arc::new(Mutex::new(None)),
+      switch_callback: Arc::new(Mutex::new(None)),
+      #[cfg(windows)]
+      device_watcher: None,
       running: Arc::new(Mutex::new(false)),
       worker_handle: None,
     }
   }
 
-  #[napi(ts_args_type = "deviceId?: string")]
-  pub fn set_device(&mut self, device_id: Option<String>) {
-    if self.device_id == device_id {
+  /// Capture the device with the given id. Without an id, the monitor follows the default
+  /// device and switches to the new one whenever it changes. Output devices are captured in
+  /// loopback mode unless `kind` is `input`; without a `kind` the current one is kept.
+  #[napi(ts_args_type = "deviceId?: string, options?: DeviceOptions")]
+  pub fn set_device(&mut self, device_id: Option<String>, options: Option<DeviceOptions>) {
+    let kind = options
+      .and_then(|options| options.kind)
+      .unwrap_or(self.kind);
+    if matches!(self.source, SourceConfig::Device)
+      && self.device_id == device_id
+      && self.kind == kind
+    {
       return;
     }
 
@@ -45,9 +141,78 @@ impl AudioMonitor {
       self.stop();
     }
 
+    self.source = SourceConfig::Device;
+    self.kind = kind;
     self.device_id = device_id;
   }
 
+  /// Whether the selected device is captured in loopback mode or as an input.
+  #[napi(getter)]
+  pub fn kind(&self) -> DeviceKind {
+    self.kind
+  }
+
+  /// Request a specific format from the capture device, or its mix format when omitted.
+  /// Int samples may have 8, 16, 24 or 32 bits, float samples 32 or 64.
+  #[napi(ts_args_type = "format?: CaptureFormat")]
+  pub fn set_capture_format(&mut self, format: Option<CaptureFormat>) -> Result<()> {
+    let capture_format = match format {
+      Some(format) => Some(StreamFormat::from_options(format)?),
+      None => None,
+    };
+
+    if self.running() {
+      self.stop();
+    }
+
+    self.capture_format = capture_format;
+    Ok(())
+  }
+
+  /// Capture only the audio played by the process `pid` and its children, e.g. a music player.
+  /// With `includeTree: false`, everything except that process tree is captured instead, which
+  /// keeps an app's own sounds out of its visuals.
+  #[napi(ts_args_type = "pid: number, options?: ProcessOptions")]
+  pub fn set_process(&mut self, pid: u32, options: Option<ProcessOptions>) -> Result<()> {
+    if pid == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "Process id must be positive".to_string(),
+      ));
+    }
+
+    if self.running() {
+      self.stop();
+    }
+
+    self.source = SourceConfig::Process {
+      process_id: pid,
+      include_tree: options.and_then(|o| o.include_tree).unwrap_or(true),
+    };
+    Ok(())
+  }
+
+  #[napi(ts_args_type = "path: string, options?: FileSourceOptions")]
+  pub fn set_file(&mut self, path: String, options: Option<FileSourceOptions>) {
+    if self.running() {
+      self.stop();
+    }
+
+    let realtime = options.and_then(|o| o.realtime).unwrap_or(true);
+    self.source = SourceConfig::File { path, realtime };
+  }
+
+  #[napi]
+  pub fn set_generator(&mut self, options: GeneratorOptions) -> Result<()> {
+    let config = GeneratorConfig::from_options(options)?;
+    if self.running() {
+      self.stop();
+    }
+
+    self.source = SourceConfig::Generator(config);
+    Ok(())
+  }
+
   #[napi(ts_args_type = "chunkSize?: number")]
   pub fn start(&mut self, chunk_size: Option<u32>) -> Result<()> {
     // 确保任何现有的播放被暂停
@@ -58,38 +223,85 @@ impl AudioMonitor {
       self.chunk_size = size as usize;
     }
 
-    // 设置运行状态为 true
-    *self
-      .running
-      .lock()
-      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))? = true;
+    self.set_current_device(None);
+    let source = self.create_source()?;
+    let follower = self.default_device_follower()?;
+    self.launch(source, follower)
+  }
 
-    // 更新设备ID（如果需要，将使用默认设备）
-    self.update_device_id();
+  /// Start monitoring frames from an arbitrary [CaptureSource] instead of the default device.
+  pub fn start_with_source(&mut self, source: Box<dyn CaptureSource>) -> Result<()> {
+    self.stop();
+    self.launch(source, None)
+  }
+
+  /// Start monitoring `source` and switch to the source created by `follower` whenever it
+  /// reports a new default device.
+  pub fn start_following(
+    &mut self,
+    source: Box<dyn CaptureSource>,
+    follower: DeviceFollower,
+  ) -> Result<()> {
+    self.stop();
+    self.launch(source, Some(follower))
+  }
+
+  // 在已停止的监控器上启动工作线程
+  fn launch(
+    &mut self,
+    source: Box<dyn CaptureSource>,
+    follower: Option<DeviceFollower>,
+  ) -> Result<()> {
+    // 设置运行状态为 true
+    set_locked(&self.running, true)?;
 
     // 创建通信通道
     let (tx_capt, rx_capt): (SyncSender<AudioData>, Receiver<AudioData>) = mpsc::sync_channel(10);
 
+    // 清除上一次的格式信息和错误
+    if let Ok(mut format) = self.format.lock() {
+      *format = None;
+    }
+    if let Ok(mut last_error) = self.last_error.lock() {
+      *last_error = None;
+    }
+
     // 复制需要传递给工作线程的值
-    let running = Arc::clone(&self.running);
-    let spectrum = Arc::clone(&self.spectrum);
-    let chunk_size = self.chunk_size;
-    let device_id = self.device_id.clone();
+    let state = WorkerState {
+      running: Arc::clone(&self.running),
+      spectrum: Arc::clone(&self.spectrum),
+      format: Arc::clone(&self.format),
+      pipeline: Arc::clone(&self.pipeline),
+      callback: Arc::clone(&self.callback),
+      readers: Arc::clone(&self.readers),
+      last_error: Arc::clone(&self.last_error),
+      error_callback: Arc::clone(&self.error_callback),
+      reconnect: Arc::clone(&self.reconnect),
+      reconnect_callback: Arc::clone(&self.reconnect_callback),
+      current_device: Arc::clone(&self.current_device),
+      switch_callback: Arc::clone(&self.switch_callback),
+    };
+    let frames = SlidingFrames::new(self.chunk_size, self.hop_size() as usize);
 
     // 创建工作线程
-    self.worker_handle = match spawn_audio_monitor_thread(
-      rx_capt, tx_capt, running, spectrum, chunk_size, device_id,
-    ) {
-      Ok(handle) => Some(handle),
-      Err(e) => return Err(Error::new(Status::GenericFailure, e.to_string())),
-    };
+    self.worker_handle =
+      match spawn_audio_monitor_thread(rx_capt, tx_capt, state, frames, source, follower) {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+          if let Ok(mut running) = self.running.lock() {
+            *running = false;
+          }
+          return Err(Error::new(Status::GenericFailure, e.to_string()));
+        }
+      };
 
     Ok(())
   }
 
   #[napi]
   pub fn stop(&mut self) {
-    if !self.running() {
+    // 数据源结束或出错时工作线程已自行停止，但仍需回收
+    if !self.running() && self.worker_handle.is_none() {
       return;
     }
 
@@ -102,26 +314,397 @@ impl AudioMonitor {
     if let Some(handle) = self.worker_handle.take() {
       let _ = handle.join();
     }
+
+    #[cfg(windows)]
+    {
+      self.device_watcher = None;
+    }
+
+    // 结束所有 `frames()` 读取器
+    if let Ok(mut readers) = self.readers.lock() {
+      readers.clear();
+    }
   }
 
+  /// Band values of the combined channels, normalised when `setAutoGain` and smoothed when
+  /// `setSmoothing` has been called.
   #[napi]
   pub fn get_spectrum(&self, num_bands: u32) -> Result<Vec<f32>> {
+    let channels = self.samples()?;
+    let mut pipeline = self.pipeline()?;
+    Ok(
+      pipeline
+        .polled(&channels, num_bands as usize)
+        .values()
+        .to_vec(),
+    )
+  }
+
+  /// Same as `getSpectrum`, as a `Float32Array` instead of a JS array. Allocates a new array
+  /// on every call; render loops can reuse one with `fillSpectrum` instead.
+  #[napi]
+  pub fn get_spectrum_array(&self, num_bands: u32) -> Result<Float32Array> {
+    Ok(Float32Array::new(self.get_spectrum(num_bands)?))
+  }
+
+  /// Write `values.length` bands of `getSpectrum` into `values` in place, so that render
+  /// loops can reuse the same array. Returns the number of bands written, which is smaller
+  /// for layouts with a fixed band count such as octaves.
+  #[napi]
+  pub fn fill_spectrum(&self, mut values: Float32Array) -> Result<u32> {
+    self.fill_bands(&mut values).map(|count| count as u32)
+  }
+
+  /// Same as `getSpectrum`, together with the peak-hold marker of every band.
+  #[napi]
+  pub fn get_spectrum_with_peaks(&self, num_bands: u32) -> Result<SpectrumFrame> {
+    let channels = self.samples()?;
+    let mut pipeline = self.pipeline()?;
+    let smoother = pipeline.polled(&channels, num_bands as usize);
+    Ok(SpectrumFrame {
+      values: smoother.values().to_vec(),
+      peaks: smoother.peaks().to_vec(),
+    })
+  }
+
+  /// Call `callback` from the processing thread whenever a new frame has been analysed,
+  /// instead of polling `getSpectrum`. Replaces the previous callback. Frames go through the
+  /// same automatic gain and smoothing settings as `getSpectrum`, with their own state.
+  #[napi(
+    ts_args_type = "callback: (frame: SpectrumEvent) => void, options?: SpectrumCallbackOptions"
+  )]
+  pub fn on_spectrum(
+    &mut self,
+    env: Env,
+    callback: JsFunction,
+    options: Option<SpectrumCallbackOptions>,
+  ) -> Result<()> {
+    let mut tsfn: ThreadsafeFunction<SpectrumEvent, ErrorStrategy::Fatal> = callback
+      .create_threadsafe_function(2, |ctx: ThreadSafeCallContext<SpectrumEvent>| {
+        Ok(vec![ctx.value])
+      })?;
+    // 回调不阻止 Node.js 进程退出
+    tsfn.unref(&env)?;
+
+    let options = options.unwrap_or(SpectrumCallbackOptions {
+      num_bands: None,
+      max_rate: None,
+    });
+    check_value("maxRate", options.max_rate, |rate| rate > 0.0, " above 0")?;
+    self.subscribe(SpectrumCallback::new(
+      // JS 处理不过来时丢弃帧，而不是阻塞处理线程
+      Box::new(move |event| {
+        tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+        true
+      }),
+      options.num_bands.unwrap_or(64) as usize,
+      options.max_rate,
+    ))
+  }
+
+  /// Async iterator over analysed frames, ending when the monitor stops. Frames that arrive
+  /// while the reader is busy are buffered. Beyond `bufferSize`, the oldest are dropped, or
+  /// with `overflow: 'block'` the analysis waits for the reader.
+  #[napi(
+    ts_args_type = "options?: FrameStreamOptions",
+    ts_return_type = "FrameReader & AsyncIterable<SpectrumEvent>"
+  )]
+  pub fn frames(&self, env: Env, options: Option<FrameStreamOptions>) -> Result<JsObject> {
+    let options = options.unwrap_or(FrameStreamOptions {
+      num_bands: None,
+      max_rate: None,
+      buffer_size: None,
+      overflow: None,
+    });
+    check_value("maxRate", options.max_rate, |rate| rate > 0.0, " above 0")?;
+    let queue = Arc::new(FrameQueue::new(
+      options.buffer_size.unwrap_or(4) as usize,
+      options.overflow.unwrap_or(FrameOverflow::DropOldest),
+    ));
+    self.add_reader(
+      FrameSender::new(Arc::clone(&queue), Arc::clone(&self.running)),
+      options.num_bands.unwrap_or(64) as usize,
+      options.max_rate,
+    )?;
+    FrameReader::create(env, queue)
+  }
+
+  /// Remove the callback registered with `onSpectrum`.
+  #[napi]
+  pub fn off_spectrum(&mut self) -> Result<()> {
+    set_locked(&self.callback, None)
+  }
+
+  /// Call `callback` when capturing fails for good, e.g. a file that cannot be read, or an
+  /// unplugged device once reconnecting is disabled or has given up. The monitor has stopped
+  /// by then and `lastError` holds the same error.
+  #[napi(ts_args_type = "callback: (error: MonitorError) => void")]
+  pub fn on_error(&mut self, env: Env, callback: JsFunction) -> Result<()> {
+    let mut tsfn: ThreadsafeFunction<MonitorError, ErrorStrategy::Fatal> = callback
+      .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<MonitorError>| {
+        Ok(vec![ctx.value])
+      })?;
+    tsfn.unref(&env)?;
+
+    self.set_error_callback(Box::new(move |error| {
+      tsfn.call(error, ThreadsafeFunctionCallMode::NonBlocking);
+    }))
+  }
+
+  /// Remove the callback registered with `onError`.
+  #[napi]
+  pub fn off_error(&mut self) -> Result<()> {
+    set_locked(&self.error_callback, None)
+  }
+
+  /// Configure how a device that was unplugged, disabled or changed its format is reopened.
+  /// The same device is used if it comes back, the new default device otherwise.
+  /// Delays must be finite and at least 0.
+  #[napi]
+  pub fn set_reconnect(&mut self, options: ReconnectOptions) -> Result<()> {
+    let backoff = Backoff::from_options(options)?;
+    set_locked(&self.reconnect, backoff)
+  }
+
+  /// Call `callback` when the stream has been reopened after the device was lost.
+  #[napi(ts_args_type = "callback: (event: ReconnectEvent) => void")]
+  pub fn on_reconnect(&mut self, env: Env, callback: JsFunction) -> Result<()> {
+    let mut tsfn: ThreadsafeFunction<ReconnectEvent, ErrorStrategy::Fatal> = callback
+      .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<ReconnectEvent>| {
+        Ok(vec![ctx.value])
+      })?;
+    tsfn.unref(&env)?;
+
+    self.set_reconnect_callback(Box::new(move |event| {
+      tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+    }))
+  }
+
+  /// Remove the callback registered with `onReconnect`.
+  #[napi]
+  pub fn off_reconnect(&mut self) -> Result<()> {
+    set_locked(&self.reconnect_callback, None)
+  }
+
+  /// Call `callback` when a monitor following the default device switched to a new device.
+  #[napi(ts_args_type = "callback: (event: DeviceSwitchEvent) => void")]
+  pub fn on_device_switch(&mut self, env: Env, callback: JsFunction) -> Result<()> {
+    let mut tsfn: ThreadsafeFunction<DeviceSwitchEvent, ErrorStrategy::Fatal> = callback
+      .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<DeviceSwitchEvent>| {
+        Ok(vec![ctx.value])
+      })?;
+    tsfn.unref(&env)?;
+
+    self.set_switch_callback(Box::new(move |event| {
+      tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+    }))
+  }
+
+  /// Remove the callback registered with `onDeviceSwitch`.
+  #[napi]
+  pub fn off_device_switch(&mut self) -> Result<()> {
+    set_locked(&self.switch_callback, None)
+  }
+
+  /// Error that stopped the monitor, `null` if it is running or was stopped normally.
+  #[napi(getter)]
+  pub fn last_error(&self) -> Option<MonitorError> {
+    self
+      .last_error
+      .lock()
+      .ok()
+      .and_then(|last_error| last_error.clone())
+  }
+
+  /// Configure attack/release smoothing and peak-hold markers of `getSpectrum`.
+  /// All values must be finite and at least 0.
+  #[napi]
+  pub fn set_smoothing(&mut self, options: SmoothingOptions) -> Result<()> {
+    let smoother = BandSmoother::from_options(options)?;
+    self.pipeline()?.set_smoother(smoother);
+    Ok(())
+  }
+
+  /// Normalise `getSpectrum` to 0-1 relative to the recent level, or disable it when omitted.
+  /// `window` must be above 0, `adaptation` at least 0, `percentile` between 0 and 100 and
+  /// `noiseGate` finite.
+  #[napi(ts_args_type = "options?: AutoGainOptions")]
+  pub fn set_auto_gain(&mut self, options: Option<AutoGainOptions>) -> Result<()> {
+    let auto_gain = options.map(AutoGain::from_options).transpose()?;
+    self.pipeline()?.set_auto_gain(auto_gain);
+    Ok(())
+  }
+
+  /// Band values of a single channel, without automatic gain or smoothing.
+  #[napi]
+  pub fn get_channel_spectrum(&self, channel: u32, num_bands: u32) -> Result<Vec<f32>> {
+    let num_bands = num_bands as usize;
+    let channels = self.samples()?;
+    let mut pipeline = self.pipeline()?;
+    let analyzer = &mut pipeline.analyzer;
+
+    if channels.is_empty() {
+      return Ok(vec![0.0; analyzer.band_ranges(num_bands).len()]);
+    }
+    match channels.get(channel as usize) {
+      Some(samples) => Ok(analyzer.analyze(samples, num_bands).to_vec()),
+      None => Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "Channel {} out of range, stream has {} channels",
+          channel,
+          channels.len()
+        ),
+      )),
+    }
+  }
+
+  #[napi]
+  pub fn set_downmix(&mut self, mode: DownmixMode) -> Result<()> {
+    self.pipeline()?.downmix = mode;
+    Ok(())
+  }
+
+  #[napi(getter)]
+  pub fn downmix(&self) -> Result<DownmixMode> {
+    Ok(self.pipeline()?.downmix)
+  }
+
+  /// Select the window function. `parameter` is beta for Kaiser windows, between 0 and 50,
+  /// and sigma for Gaussian windows, above 0.
+  #[napi(ts_args_type = "window: WindowFunction, parameter?: number")]
+  pub fn set_window(&mut self, window: WindowFunction, parameter: Option<f64>) -> Result<()> {
+    let function = Window::from_options(window, parameter)?;
+    self.pipeline()?.analyzer.set_window(function);
+    self.window = window;
+    Ok(())
+  }
+
+  #[napi(getter)]
+  pub fn window(&self) -> WindowFunction {
+    self.window
+  }
+
+  /// Choose how FFT bins are grouped into bands, e.g. logarithmic or third-octave bands.
+  /// The maximum frequency must be above the minimum, and at least 1 Hz for logarithmic
+  /// and octave layouts.
+  #[napi]
+  pub fn set_band_layout(&mut self, layout: BandLayoutOptions) -> Result<()> {
+    let band_layout = BandLayout::from_options(layout)?;
+    self.pipeline()?.analyzer.set_layout(band_layout);
+    self.band_scale = band_layout.scale;
+    Ok(())
+  }
+
+  #[napi(getter)]
+  pub fn band_scale(&self) -> BandScale {
+    self.band_scale
+  }
+
+  /// Choose the unit of the spectrum values: the original log scale, dBFS, or 0-1 between a dB floor and ceiling.
+  #[napi]
+  pub fn set_scale(&mut self, options: ScaleOptions) -> Result<()> {
+    let floor = options.floor.map_or(DEFAULT_FLOOR, |f| f as f32);
+    let ceiling = options.ceiling.map_or(DEFAULT_CEILING, |c| c as f32);
+    if ceiling <= floor {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Ceiling {} dB must be above floor {} dB", ceiling, floor),
+      ));
+    }
+
+    self
+      .pipeline()?
+      .analyzer
+      .set_scale(options.scale, floor, ceiling);
+    self.scale = options.scale;
+    Ok(())
+  }
+
+  #[napi(getter)]
+  pub fn scale(&self) -> SpectrumScale {
+    self.scale
+  }
+
+  /// Weight the spectrum and the level meter by a frequency curve, e.g. A-weighting.
+  #[napi]
+  pub fn set_weighting(&mut self, weighting: FrequencyWeighting) -> Result<()> {
+    self.pipeline()?.analyzer.set_weighting(weighting);
+    self.weighting = weighting;
+    Ok(())
+  }
+
+  #[napi(getter)]
+  pub fn weighting(&self) -> FrequencyWeighting {
+    self.weighting
+  }
+
+  /// Weighted level of the combined channels in dBFS, where a full scale sine reads 0 dB.
+  /// Never below the floor set with `setScale`.
+  #[napi]
+  pub fn get_level(&self) -> Result<f64> {
+    let channels = self.samples()?;
+    let level = self.pipeline()?.level(&channels);
+    Ok(level as f64)
+  }
+
+  #[napi(getter)]
+  pub fn channels(&self) -> u32 {
     self
       .spectrum
       .lock()
-      .map(|spectrum| {
-        if spectrum.is_empty() {
-          vec![0.0; num_bands as usize]
-        } else {
-          fft::analyze_spectrum(&spectrum, num_bands as usize)
-        }
-      })
-      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+      .map(|channels| channels.len() as u32)
+      .unwrap_or(0)
+  }
+
+  /// Centre frequency in Hz of every band returned by `getSpectrum`.
+  /// Assumes 48 kHz until the stream has been opened.
+  #[napi]
+  pub fn get_band_frequencies(&self, num_bands: u32) -> Result<Vec<f64>> {
+    Ok(
+      self
+        .pipeline()?
+        .analyzer
+        .band_ranges(num_bands as usize)
+        .iter()
+        .map(|band| band.centre as f64)
+        .collect(),
+    )
+  }
+
+  /// Format of the stream being analysed, `null` until the source has been opened.
+  #[napi(getter)]
+  pub fn format(&self) -> Option<StreamInfo> {
+    self
+      .format
+      .lock()
+      .ok()
+      .and_then(|format| format.as_ref().map(StreamInfo::from))
+  }
+
+  #[napi(getter)]
+  pub fn sample_rate(&self) -> Option<u32> {
+    self
+      .format
+      .lock()
+      .ok()
+      .and_then(|format| format.map(|f| f.sample_rate))
   }
 
+  /// Id of the device being captured, or of the one selected with `setDevice` until started.
   #[napi(getter)]
   pub fn current_device_id(&self) -> Result<Option<String>> {
-    Ok(self.device_id.clone())
+    let current = self
+      .current_device
+      .lock()
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    Ok(current.clone().or_else(|| self.device_id.clone()))
+  }
+
+  /// Whether the monitor follows the default device of its kind, i.e. `setDevice` was called without an id.
+  #[napi(getter)]
+  pub fn follows_default_device(&self) -> bool {
+    matches!(self.source, SourceConfig::Device) && self.device_id.is_none()
   }
 
   #[napi(getter)]
@@ -134,33 +717,189 @@ impl AudioMonitor {
     self.chunk_size as u32
   }
 
-  fn update_device_id(&mut self) {
-    // 检查指定的设备是否存在
-    if let Some(id) = &self.device_id {
-      if get_output_device_by_id(id.clone()).is_some() {
-        debug!("Using specified device: {}", id);
-        return;
-      }
-      debug!("Device not found: {}, will use default device", id);
+  /// Set the analysis frame length and how many new samples arrive between two frames,
+  /// e.g. an 8192-point FFT every 368 samples for 120 updates per second at 44.1 kHz.
+  /// Stops the monitor if it is running.
+  #[napi]
+  pub fn set_frame_options(&mut self, options: FrameOptions) -> Result<()> {
+    if options.fft_size == Some(0) || options.hop_size == Some(0) {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "FFT and hop size must be positive".to_string(),
+      ));
     }
 
-    // 获取默认设备作为备选
-    match get_default_device(&Direction::Render) {
-      Ok(device) => match device.get_id() {
-        Ok(default_id) => {
-          debug!("Using default device: {}", default_id);
-          self.device_id = Some(default_id);
-        }
-        Err(_) => {
-          debug!("Unable to get default device ID");
-          self.device_id = None;
-        }
-      },
-      Err(e) => {
-        error!("Unable to get default device: {}", e);
-        self.device_id = None;
-      }
+    if self.running() {
+      self.stop();
+    }
+
+    if let Some(size) = options.fft_size {
+      self.chunk_size = size as usize;
     }
+    self.hop_size = options.hop_size.map(|hop| hop as usize);
+    Ok(())
+  }
+
+  /// Number of new samples between two analysis frames.
+  #[napi(getter)]
+  pub fn hop_size(&self) -> u32 {
+    self
+      .hop_size
+      .unwrap_or(self.chunk_size)
+      .min(self.chunk_size) as u32
+  }
+
+  // 将平滑后的频谱写入调用方提供的缓冲区，返回写入的频段数
+  fn fill_bands(&self, values: &mut [f32]) -> Result<usize> {
+    let channels = self.samples()?;
+    let mut pipeline = self.pipeline()?;
+    let smoothed = pipeline.polled(&channels, values.len()).values();
+    let count = values.len().min(smoothed.len());
+    values[..count].copy_from_slice(&smoothed[..count]);
+    Ok(count)
+  }
+
+  // 注册频谱回调，替换之前的回调
+  fn subscribe(&mut self, callback: SpectrumCallback) -> Result<()> {
+    set_locked(&self.callback, Some(callback))
+  }
+
+  // 注册错误回调，替换之前的回调
+  fn set_error_callback(&mut self, callback: ErrorCallback) -> Result<()> {
+    set_locked(&self.error_callback, Some(callback))
+  }
+
+  // 注册重连回调，替换之前的回调
+  fn set_reconnect_callback(&mut self, callback: ReconnectCallback) -> Result<()> {
+    set_locked(&self.reconnect_callback, Some(callback))
+  }
+
+  // 注册设备切换回调，替换之前的回调
+  fn set_switch_callback(&mut self, callback: SwitchCallback) -> Result<()> {
+    set_locked(&self.switch_callback, Some(callback))
+  }
+
+  fn set_current_device(&self, device_id: Option<String>) {
+    if let Ok(mut current) = self.current_device.lock() {
+      *current = device_id;
+    }
+  }
+
+  // 添加读取器，监控器停止时丢弃发送端以结束读取
+  fn add_reader(&self, sender: FrameSender, num_bands: usize, max_rate: Option<f64>) -> Result<()> {
+    self
+      .readers
+      .lock()
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?
+      .push(SpectrumCallback::new(
+        Box::new(move |event| sender.send(event)),
+        num_bands,
+        max_rate,
+      ));
+    Ok(())
+  }
+
+  fn samples(&self) -> Result<MutexGuard<'_, AudioData>> {
+    self
+      .spectrum
+      .lock()
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+  }
+
+  // 锁定处理流程，并同步当前音频流的采样率
+  fn pipeline(&self) -> Result<MutexGuard<'_, SpectrumPipeline>> {
+    let mut pipeline = self
+      .pipeline
+      .lock()
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    if let Some(sample_rate) = self.sample_rate() {
+      pipeline.analyzer.set_sample_rate(sample_rate);
+    }
+    Ok(pipeline)
+  }
+
+  fn create_source(&mut self) -> Result<Box<dyn CaptureSource>> {
+    match &self.source {
+      SourceConfig::Device => self.device_source(),
+      SourceConfig::Process {
+        process_id,
+        include_tree,
+      } => self.process_source(*process_id, *include_tree),
+      SourceConfig::File { path, realtime } => Ok(Box::new(WavSource::new(path, *realtime))),
+      SourceConfig::Generator(config) => Ok(Box::new(config.source())),
+    }
+  }
+
+  // 设备在采集线程中解析（该线程已初始化 COM），实际打开的设备由 `open_source` 发布
+  #[cfg(windows)]
+  fn device_source(&mut self) -> Result<Box<dyn CaptureSource>> {
+    Ok(Box::new(DeviceSource::new(
+      self.kind,
+      self.device_id.clone(),
+      self.capture_format,
+    )))
+  }
+
+  #[cfg(not(windows))]
+  fn device_source(&mut self) -> Result<Box<dyn CaptureSource>> {
+    Err(Error::new(
+      Status::GenericFailure,
+      "Device capture is only available on Windows".to_string(),
+    ))
+  }
+
+  #[cfg(windows)]
+  fn process_source(&self, process_id: u32, include_tree: bool) -> Result<Box<dyn CaptureSource>> {
+    Ok(Box::new(ProcessSource::new(
+      process_id,
+      include_tree,
+      self.capture_format,
+    )))
+  }
+
+  #[cfg(not(windows))]
+  fn process_source(
+    &self,
+    _process_id: u32,
+    _include_tree: bool,
+  ) -> Result<Box<dyn CaptureSource>> {
+    Err(Error::new(
+      Status::GenericFailure,
+      "Process capture is only available on Windows".to_string(),
+    ))
+  }
+
+  // 未指定设备时由设备通知驱动，跟随默认设备切换
+  #[cfg(windows)]
+  fn default_device_follower(&mut self) -> Result<Option<DeviceFollower>> {
+    if !self.follows_default_device() {
+      return Ok(None);
+    }
+    let mut watcher = DeviceWatcher::new()?;
+    let notifier = watcher.default_changes()?;
+    self.device_watcher = Some(watcher);
+
+    // 当前设备由采集线程在打开后告知
+    let (kind, format) = (self.kind, self.capture_format);
+    Ok(Some(DeviceFollower::new(
+      Box::new(notifier),
+      kind.into(),
+      None,
+      Box::new(move |device_id| {
+        Box::new(DeviceSource::new(kind, Some(device_id.to_string()), format))
+      }),
+    )))
+  }
+
+  #[cfg(not(windows))]
+  fn default_device_follower(&mut self) -> Result<Option<DeviceFollower>> {
+    Ok(None)
+  }
+}
+
+impl Default for AudioMonitor {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
@@ -170,56 +909,161 @@ impl Drop for AudioMonitor {
   }
 }
 
+// `onSpectrum` 注册的回调或 `frames()` 读取器，`emit` 返回 false 表示接收端已关闭
+struct SpectrumCallback {
+  emit: Box<dyn Fn(SpectrumEvent) -> bool + Send>,
+  num_bands: usize,
+  min_interval: Duration,
+  last_emit: Option<Instant>,
+  bands: BandState,
+}
+
+impl SpectrumCallback {
+  fn new(
+    emit: Box<dyn Fn(SpectrumEvent) -> bool + Send>,
+    num_bands: usize,
+    max_rate: Option<f64>,
+  ) -> Self {
+    SpectrumCallback {
+      emit,
+      num_bands,
+      // 极小的频率超出 Duration 的范围，视为最长间隔
+      min_interval: max_rate
+        .filter(|rate| *rate > 0.0)
+        .map_or(Duration::ZERO, |rate| {
+          Duration::try_from_secs_f64(1.0 / rate).unwrap_or(Duration::MAX)
+        }),
+      last_emit: None,
+      bands: BandState::default(),
+    }
+  }
+
+  // 距上次回调是否已超过最小间隔
+  fn due(&self, now: Instant) -> bool {
+    self
+      .last_emit
+      .is_none_or(|last| now.duration_since(last) >= self.min_interval)
+  }
+}
+
+// 工作线程与监控器共享的状态
+#[derive(Clone)]
+struct WorkerState {
+  running: Arc<Mutex<bool>>,
+  spectrum: Arc<Mutex<AudioData>>,
+  format: Arc<Mutex<Option<StreamFormat>>>,
+  pipeline: Arc<Mutex<SpectrumPipeline>>,
+  callback: Arc<Mutex<Option<SpectrumCallback>>>,
+  readers: Arc<Mutex<Vec<SpectrumCallback>>>,
+  last_error: Arc<Mutex<Option<MonitorError>>>,
+  error_callback: Arc<Mutex<Option<ErrorCallback>>>,
+  reconnect: Arc<Mutex<Option<Backoff>>>,
+  reconnect_callback: Arc<Mutex<Option<ReconnectCallback>>>,
+  current_device: Arc<Mutex<Option<String>>>,
+  switch_callback: Arc<Mutex<Option<SwitchCallback>>>,
+}
+
 // 创建音频监控线程
 fn spawn_audio_monitor_thread(
   rx_capt: Receiver<AudioData>,
   tx_capt: SyncSender<AudioData>,
-  running: Arc<Mutex<bool>>,
-  spectrum: Arc<Mutex<AudioData>>,
-  chunk_size: usize,
-  device_id: Option<String>,
+  state: WorkerState,
+  frames: SlidingFrames,
+  source: Box<dyn CaptureSource>,
+  follower: Option<DeviceFollower>,
 ) -> std::result::Result<JoinHandle<()>, std::io::Error> {
   thread::Builder::new()
     .name("AudioMonitor".to_string())
     .spawn(move || {
       // 创建音频捕获线程
+      let capture_state = state.clone();
       let capture_thread = thread::Builder::new()
-        .name("LoopbackCapture".to_string())
-        .spawn(move || {
-          if let Err(err) = loopback_capture_loop(tx_capt, chunk_size, device_id) {
-            error!("Loopback capture failed with error {}", err);
-          }
-        })
+        .name("AudioCapture".to_string())
+        .spawn(move || capture_loop(source, tx_capt, &capture_state, frames, follower))
         .unwrap_or_else(|e| {
           error!("Failed to spawn capture thread: {}", e);
           panic!("Critical thread creation failure");
         });
 
       // 主循环处理接收到的音频数据
-      process_audio_data(rx_capt, running, spectrum);
+      process_audio_data(rx_capt, &state);
 
       // 等待捕获线程结束
-      if let Err(e) = capture_thread.join() {
-        error!("Error joining capture thread: {:?}", e);
-      }
+      let error = match capture_thread.join() {
+        Ok(Ok(())) => None,
+        Ok(Err(err)) => {
+          error!("Audio capture failed with error {}", err);
+          Some(monitor_error(&err))
+        }
+        Err(payload) => {
+          let err = CaptureError::Panicked(panic_message(payload.as_ref()));
+          error!("Audio capture failed with error {}", err);
+          Some(monitor_error(&err))
+        }
+      };
+      finish(&state, error);
     })
 }
 
+// panic 的消息，通常是 `&str` 或 `String`
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "unknown panic".to_string()
+  }
+}
+
+fn monitor_error(err: &CaptureError) -> MonitorError {
+  MonitorError {
+    code: err.code(),
+    message: err.to_string(),
+    hresult: err.hresult(),
+    timestamp: timestamp(),
+  }
+}
+
+// 工作线程退出时更新状态：记录错误、结束读取器，最后通知错误回调
+fn finish(state: &WorkerState, error: Option<MonitorError>) {
+  if let (Some(error), Ok(mut last_error)) = (&error, state.last_error.lock()) {
+    *last_error = Some(error.clone());
+  }
+  if let Ok(mut running) = state.running.lock() {
+    *running = false;
+  }
+  if let Ok(mut readers) = state.readers.lock() {
+    readers.clear();
+  }
+  if let (Some(error), Ok(callback)) = (error, state.error_callback.lock()) {
+    if let Some(callback) = callback.as_ref() {
+      callback(error);
+    }
+  }
+}
+
+// 当前时间，自 Unix 纪元起的毫秒数
+pub(crate) fn timestamp() -> f64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0.0, |time| time.as_secs_f64() * 1000.0)
+}
+
 // 处理音频数据的主循环
-fn process_audio_data(
-  rx_capt: Receiver<AudioData>,
-  running: Arc<Mutex<bool>>,
-  spectrum: Arc<Mutex<AudioData>>,
-) {
-  while match running.lock() {
+fn process_audio_data(rx_capt: Receiver<AudioData>, state: &WorkerState) {
+  let mut sequence = 0;
+  while match state.running.lock() {
     Ok(guard) => *guard,
     Err(_) => false,
   } {
     match rx_capt.recv_timeout(Duration::from_millis(100)) {
       Ok(samples) => {
-        if let Ok(mut spec) = spectrum.lock() {
+        if let Ok(mut spec) = state.spectrum.lock() {
           *spec = samples;
         }
+        notify_spectrum(state, sequence);
+        sequence += 1;
       }
       Err(mpsc::RecvTimeoutError::Timeout) => continue,
       Err(mpsc::RecvTimeoutError::Disconnected) => break,
@@ -227,87 +1071,823 @@ fn process_audio_data(
   }
 }
 
-fn loopback_capture_loop(
+// 将当前帧推送给回调和读取器，并移除已关闭的读取器
+fn notify_spectrum(state: &WorkerState, sequence: i64) {
+  let now = Instant::now();
+  if let Ok(mut callback) = state.callback.lock() {
+    if let Some(listener) = callback.as_mut() {
+      if !deliver(state, listener, sequence, now) {
+        *callback = None;
+      }
+    }
+  }
+  // 推送期间不持有锁，阻塞的读取器不会妨碍 JS 线程添加读取器或停止监控器
+  let Ok(mut readers) = state
+    .readers
+    .lock()
+    .map(|mut readers| mem::take(&mut *readers))
+  else {
+    return;
+  };
+  readers.retain_mut(|reader| deliver(state, reader, sequence, now));
+  if let Ok(mut current) = state.readers.lock() {
+    readers.append(&mut current);
+    *current = readers;
+  }
+}
+
+// 未超过频率限制时分析当前帧并交给接收端，返回接收端是否仍然有效
+fn deliver(
+  state: &WorkerState,
+  listener: &mut SpectrumCallback,
+  sequence: i64,
+  now: Instant,
+) -> bool {
+  if !listener.due(now) {
+    return true;
+  }
+  listener.last_emit = Some(now);
+
+  let sample_rate = state
+    .format
+    .lock()
+    .ok()
+    .and_then(|f| f.map(|f| f.sample_rate));
+  let (Ok(channels), Ok(mut pipeline)) = (state.spectrum.lock(), state.pipeline.lock()) else {
+    return true;
+  };
+  if let Some(sample_rate) = sample_rate {
+    pipeline.analyzer.set_sample_rate(sample_rate);
+  }
+  let smoother = pipeline.smoothed(&channels, listener.num_bands, &mut listener.bands);
+  let event = SpectrumEvent {
+    values: smoother.values().to_vec(),
+    peaks: smoother.peaks().to_vec(),
+    timestamp: timestamp(),
+    sequence,
+  };
+  // 回调在释放锁之后执行
+  drop(pipeline);
+  drop(channels);
+  (listener.emit)(event)
+}
+
+fn capture_loop(
+  mut source: Box<dyn CaptureSource>,
   tx_capt: SyncSender<AudioData>,
-  chunk_size: usize,
-  device_id: Option<String>,
-) -> std::result::Result<(), Box<dyn std::error::Error>> {
-  // 获取音频设备
-  let device = get_audio_device(device_id)?;
-
-  // 初始化音频客户端
-  let mut audio_client = device.get_iaudioclient()?;
-  let desired_format = WaveFormat::new(32, 32, &SampleType::Float, 44100, 2, None);
-  let blockalign = desired_format.get_blockalign();
-
-  debug!("Desired capture format: {:?}", desired_format);
-  let (_, min_time) = audio_client.get_periods()?;
-
-  audio_client.initialize_client(
-    &desired_format,
-    min_time,
-    &Direction::Capture,
-    &ShareMode::Shared,
-    true,
-  )?;
-
-  let h_event = audio_client.set_get_eventhandle()?;
-  let buffer_frame_count = audio_client.get_bufferframecount()?;
-  let capture_client = audio_client.get_audiocaptureclient()?;
-
-  // 样本队列，缓存从设备读取的数据
-  let mut sample_queue: VecDeque<u8> =
-    VecDeque::with_capacity(100 * blockalign as usize * (1024 + 2 * buffer_frame_count as usize));
+  state: &WorkerState,
+  mut frames: SlidingFrames,
+  mut follower: Option<DeviceFollower>,
+) -> CaptureRes<()> {
+  let mut decoder = open_source(source.as_mut(), state)?;
+  if let (Some(follower), Some(device_id)) = (follower.as_mut(), source.device_id()) {
+    follower.set_current(device_id);
+  }
 
-  // 开始音频流
-  audio_client.start_stream()?;
-  info!("Audio capture started");
+  // 样本队列，缓存从数据源读取的数据
+  let mut sample_queue: VecDeque<u8> =
+    VecDeque::with_capacity(4 * decoder.block_align() * frames.size());
+  // 滑动窗口，每 hop 个样本输出一帧最近的 size 个样本
+  let hop_size = frames.hop();
 
-  loop {
+  let result = loop {
     // 当积累了足够的样本时，处理并发送它们
-    if sample_queue.len() >= (blockalign as usize * chunk_size) {
-      let float_samples = extract_float_samples(&mut sample_queue, chunk_size, blockalign as usize);
+    if sample_queue.len() >= (decoder.block_align() * hop_size) {
+      let float_samples = extract_float_samples(&mut sample_queue, hop_size, &decoder);
 
       // 发送处理好的样本，如果接收端已关闭则退出循环
-      if tx_capt.send(float_samples).is_err() {
-        break;
+      if let Some(frame) = frames.push(&float_samples) {
+        if tx_capt.send(frame).is_err() {
+          break Ok(());
+        }
       }
+      continue;
     }
 
-    // 从设备读取数据到队列
-    capture_client.read_from_device_to_deque(&mut sample_queue)?;
+    // 数据源一直没有数据时（例如静音的环回设备）也要响应停止
+    if !state
+      .running
+      .lock()
+      .map(|running| *running)
+      .unwrap_or(false)
+    {
+      break Ok(());
+    }
 
-    // 等待事件或超时
-    if h_event.wait_for_event(100).is_err() {
-      continue;
+    // 从数据源读取数据到队列，默认设备改变时切换到新设备
+    let mut read = source.read(&mut sample_queue);
+    if let (Ok(true), Some(follower)) = (&read, follower.as_mut()) {
+      if let Some(switch) = follower.poll() {
+        sample_queue.clear();
+        match switch_device(&mut source, follower, switch, state) {
+          Ok(reopened) => decoder = reopened,
+          Err(err) => read = Err(err),
+        }
+      }
+    }
+
+    match read {
+      Ok(true) => {}
+      Ok(false) => break Ok(()),
+      Err(err) if source.recoverable(&err) => {
+        // 丢弃旧流中不完整的数据，格式可能已经改变
+        sample_queue.clear();
+        match reconnect(source.as_mut(), err, state) {
+          Ok(Some(reopened)) => {
+            decoder = reopened;
+            // 重连时可能换成了新的默认设备
+            if let (Some(follower), Some(device_id)) = (follower.as_mut(), source.device_id()) {
+              follower.set_current(device_id);
+            }
+          }
+          Ok(None) => break Ok(()),
+          Err(err) => break Err(err),
+        }
+      }
+      Err(err) => break Err(err),
     }
+  };
+
+  source.stop();
+  result
+}
+
+// 停止当前数据源并打开新的默认设备
+fn switch_device(
+  source: &mut Box<dyn CaptureSource>,
+  follower: &DeviceFollower,
+  switch: DeviceSwitch,
+  state: &WorkerState,
+) -> CaptureRes<PcmDecoder> {
+  info!("Default device changed, switching to {}", switch.device_id);
+  source.stop();
+  *source = follower.source(&switch.device_id);
+  if let Ok(mut current) = state.current_device.lock() {
+    *current = Some(switch.device_id.clone());
   }
+  let decoder = open_source(source.as_mut(), state)?;
 
-  // 停止音频流
-  let _ = audio_client.stop_stream();
-  info!("Audio capture stopped");
+  if let Ok(callback) = state.switch_callback.lock() {
+    if let Some(callback) = callback.as_ref() {
+      callback(DeviceSwitchEvent {
+        previous_id: switch.previous_id,
+        device_id: switch.device_id,
+        timestamp: timestamp(),
+      });
+    }
+  }
+  Ok(decoder)
+}
 
-  Ok(())
+// 打开数据源并发布其格式，以及实际打开的设备（重连时可能换成了默认设备）
+fn open_source(source: &mut dyn CaptureSource, state: &WorkerState) -> CaptureRes<PcmDecoder> {
+  source.open()?;
+  let format = source.format();
+  let decoder = PcmDecoder::new(&format)?;
+  if let Ok(mut state) = state.format.lock() {
+    *state = Some(format);
+  }
+  if let (Some(device_id), Ok(mut current)) = (source.device_id(), state.current_device.lock()) {
+    *current = Some(device_id);
+  }
+  Ok(decoder)
 }
 
-// 获取音频设备，优先使用指定ID的设备，如果不存在则使用默认设备
-fn get_audio_device(
-  device_id: Option<String>,
-) -> std::result::Result<Device, Box<dyn std::error::Error>> {
-  match device_id {
-    Some(id) => match get_output_device_by_id(id.clone()) {
-      Some(device) => {
-        debug!("Successfully got device: {}", id);
-        Ok(device)
+// 按指数退避重新打开数据源。监控器停止时返回 None，放弃重连或遇到不可恢复的错误时返回该错误
+fn reconnect(
+  source: &mut dyn CaptureSource,
+  error: CaptureError,
+  state: &WorkerState,
+) -> CaptureRes<Option<PcmDecoder>> {
+  let Some(backoff) = state.reconnect.lock().ok().and_then(|backoff| *backoff) else {
+    return Err(error);
+  };
+  warn!("Audio capture interrupted, reconnecting: {}", error);
+  source.stop();
+
+  let (code, message) = (error.code(), error.to_string());
+  let mut last_error = error;
+  let mut attempt = 0;
+  loop {
+    attempt += 1;
+    let Some(delay) = backoff.delay(attempt) else {
+      return Err(last_error);
+    };
+    if !sleep_while_running(delay, &state.running) {
+      return Ok(None);
+    }
+
+    match open_source(source, state) {
+      Ok(decoder) => {
+        info!("Audio capture reconnected after {} attempts", attempt);
+        if let Ok(callback) = state.reconnect_callback.lock() {
+          if let Some(callback) = callback.as_ref() {
+            callback(ReconnectEvent {
+              code,
+              message,
+              attempts: attempt,
+              timestamp: timestamp(),
+            });
+          }
+        }
+        return Ok(Some(decoder));
       }
-      None => {
-        debug!("Device not found during capture: {}, using default", id);
-        get_default_device(&Direction::Render).map_err(|e| e.into())
+      Err(err) if source.recoverable(&err) => {
+        debug!("Reconnect attempt {} failed: {}", attempt, err);
+        source.stop();
+        last_error = err;
       }
-    },
-    None => {
-      debug!("No device ID specified, using default");
-      get_default_device(&Direction::Render).map_err(|e| e.into())
+      Err(err) => return Err(err),
+    }
+  }
+}
+
+// 分段等待，以便停止监控器时及时退出。返回监控器是否仍在运行
+fn sleep_while_running(duration: Duration, running: &Mutex<bool>) -> bool {
+  let deadline = Instant::now() + duration;
+  loop {
+    if !running.lock().map(|running| *running).unwrap_or(false) {
+      return false;
+    }
+    let now = Instant::now();
+    if now >= deadline {
+      return true;
+    }
+    thread::sleep((deadline - now).min(Duration::from_millis(50)));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::source::{Generator, GeneratorSource, Signal};
+  use crate::test_alloc::count_allocations;
+  use crate::types::ErrorCode;
+  use crate::types::SignalKind;
+  use crate::types::{DeviceDirection, DeviceRole};
+  use crate::watcher::{DeviceNotification, ScriptedNotifier};
+  use std::time::Instant;
+
+  #[test]
+  fn analyses_generated_tone() {
+    let mut monitor = AudioMonitor::new();
+    let generator = Generator::new(Signal::Sine { frequency: 1000.0 }, 44100, 0.5);
+    let source = GeneratorSource::new(generator, 2, false, None);
+    monitor.start_with_source(Box::new(source)).unwrap();
+
+    let started = Instant::now();
+    let mut spectrum = vec![0.0; 64];
+    while spectrum.iter().all(|&v| v == 0.0) && started.elapsed() < Duration::from_secs(5) {
+      thread::sleep(Duration::from_millis(10));
+      spectrum = monitor.get_spectrum(64).unwrap();
+    }
+    monitor.stop();
+
+    let peak = (0..spectrum.len())
+      .max_by(|&a, &b| spectrum[a].total_cmp(&spectrum[b]))
+      .unwrap();
+    assert_eq!(peak, 2);
+    assert!(!monitor.running());
+  }
+
+  #[test]
+  fn fills_caller_supplied_buffer() {
+    let mut monitor = AudioMonitor::new();
+    monitor
+      .set_generator(GeneratorOptions {
+        signal: SignalKind::Sine,
+        frequency: Some(1000.0),
+        frequencies: None,
+        end_frequency: None,
+        period: None,
+        amplitude: None,
+        sample_rate: Some(44100),
+        channels: Some(1),
+        duration: None,
+        realtime: Some(false),
+      })
+      .unwrap();
+
+    let mut values = vec![-1.0; 64];
+    assert_eq!(monitor.fill_bands(&mut values).unwrap(), 64);
+    assert!(values.iter().all(|&v| v == 0.0));
+
+    monitor.start(None).unwrap();
+    let started = Instant::now();
+    while values.iter().all(|&v| v == 0.0) && started.elapsed() < Duration::from_secs(5) {
+      thread::sleep(Duration::from_millis(10));
+      monitor.fill_bands(&mut values).unwrap();
+    }
+    monitor.stop();
+
+    let peak = (0..values.len())
+      .max_by(|&a, &b| values[a].total_cmp(&values[b]))
+      .unwrap();
+    assert_eq!(peak, 2);
+  }
+
+  #[test]
+  fn filling_spectra_does_not_allocate() {
+    let monitor = AudioMonitor::new();
+    let tone: Vec<f32> = (0..2048).map(|i| (i as f32 * 0.3).sin()).collect();
+    *monitor.spectrum.lock().unwrap() = vec![tone.clone(), tone];
+    monitor
+      .pipeline
+      .lock()
+      .unwrap()
+      .set_smoother(BandSmoother::new(0.05, 0.2, 0.5, 1.0));
+    // 窗口为 0 时每一帧都会淘汰上一帧的历史
+    monitor
+      .pipeline
+      .lock()
+      .unwrap()
+      .set_auto_gain(Some(AutoGain::new(0.0, 90.0, true, 1.0, None)));
+
+    let mut values = vec![0.0; 64];
+    for mode in [DownmixMode::Mono, DownmixMode::Max] {
+      monitor.pipeline.lock().unwrap().downmix = mode;
+      monitor.fill_bands(&mut values).unwrap();
+      let allocated = count_allocations(|| {
+        for _ in 0..100 {
+          monitor.fill_bands(&mut values).unwrap();
+        }
+      });
+      assert_eq!(allocated, 0, "{:?}", mode);
+    }
+  }
+
+  #[test]
+  fn pushes_frames_to_the_callback() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor
+      .subscribe(SpectrumCallback::new(
+        Box::new(move |event| tx.send(event).is_ok()),
+        64,
+        None,
+      ))
+      .unwrap();
+    let generator = Generator::new(Signal::Sine { frequency: 1000.0 }, 44100, 0.5);
+    let source = GeneratorSource::new(generator, 2, false, None);
+    monitor.start_with_source(Box::new(source)).unwrap();
+
+    let events: Vec<SpectrumEvent> = (0..5)
+      .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+      .collect();
+    monitor.stop();
+
+    for (i, event) in events.iter().enumerate() {
+      assert_eq!(event.sequence, i as i64);
+      assert_eq!(event.values.len(), 64);
+      assert!(event.timestamp > 0.0);
+    }
+    let last = &events[4].values;
+    let peak = (0..last.len())
+      .max_by(|&a, &b| last[a].total_cmp(&last[b]))
+      .unwrap();
+    assert_eq!(peak, 2);
+  }
+
+  #[test]
+  fn blocking_readers_get_every_frame() {
+    let mut monitor = AudioMonitor::new();
+    let queue = Arc::new(FrameQueue::new(2, FrameOverflow::Block));
+    monitor
+      .add_reader(
+        FrameSender::new(Arc::clone(&queue), Arc::clone(&monitor.running)),
+        16,
+        None,
+      )
+      .unwrap();
+    let generator = Generator::new(Signal::WhiteNoise, 44100, 0.5);
+    let source = GeneratorSource::new(generator, 2, false, None);
+    monitor.start_with_source(Box::new(source)).unwrap();
+
+    // 读取很慢，但不限速的生成器不会让读取器丢帧
+    let mut sequences = Vec::new();
+    for _ in 0..10 {
+      thread::sleep(Duration::from_millis(5));
+      let (tx, rx) = mpsc::channel();
+      queue.pop(move |frame| {
+        let _ = tx.send(frame);
+      });
+      sequences.push(
+        rx.recv_timeout(Duration::from_secs(5))
+          .unwrap()
+          .unwrap()
+          .sequence,
+      );
+    }
+    assert_eq!(sequences, (0..10).collect::<Vec<i64>>());
+
+    // 读取器阻塞时仍然可以停止监控器
+    monitor.stop();
+    assert!(!monitor.running());
+  }
+
+  #[test]
+  fn callback_rate_is_limited() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor
+      .subscribe(SpectrumCallback::new(
+        Box::new(move |event| tx.send(event).is_ok()),
+        16,
+        Some(20.0),
+      ))
+      .unwrap();
+    let generator = Generator::new(Signal::WhiteNoise, 44100, 0.5);
+    let source = GeneratorSource::new(generator, 2, false, None);
+    monitor.start_with_source(Box::new(source)).unwrap();
+
+    thread::sleep(Duration::from_millis(300));
+    monitor.stop();
+    let events: Vec<SpectrumEvent> = rx.try_iter().collect();
+
+    // 不限速的生成器每秒产生上千帧，限速后 300 ms 内最多 7 帧
+    assert!(!events.is_empty() && events.len() <= 7, "{}", events.len());
+    assert!(events
+      .windows(2)
+      .all(|pair| pair[1].sequence > pair[0].sequence + 1));
+  }
+
+  #[test]
+  fn set_device_keeps_the_kind_when_omitted() {
+    let mut monitor = AudioMonitor::new();
+    assert_eq!(monitor.kind(), DeviceKind::Loopback);
+    let input = DeviceOptions {
+      kind: Some(DeviceKind::Input),
+    };
+    monitor.set_device(None, Some(input));
+    monitor.set_device(Some("microphone".to_string()), None);
+    assert_eq!(monitor.kind(), DeviceKind::Input);
+    monitor.set_device(None, Some(DeviceOptions { kind: None }));
+    assert_eq!(monitor.kind(), DeviceKind::Input);
+
+    let loopback = DeviceOptions {
+      kind: Some(DeviceKind::Loopback),
+    };
+    monitor.set_device(None, Some(loopback));
+    assert_eq!(monitor.kind(), DeviceKind::Loopback);
+  }
+
+  #[test]
+  fn tiny_rates_never_emit_again() {
+    let callback = SpectrumCallback::new(Box::new(|_| true), 16, Some(1e-320));
+    assert_eq!(callback.min_interval, Duration::MAX);
+  }
+
+  #[test]
+  fn capture_errors_stop_the_monitor() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor
+      .set_error_callback(Box::new(move |error| {
+        let _ = tx.send(error);
+      }))
+      .unwrap();
+    let source = WavSource::new("does-not-exist.wav", false);
+    monitor.start_with_source(Box::new(source)).unwrap();
+
+    let error = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(error.code, ErrorCode::Io);
+    assert!(!monitor.running());
+    assert_eq!(monitor.last_error().unwrap().message, error.message);
+
+    // 重新启动时清除上一次的错误
+    let generator = Generator::new(Signal::Silence, 44100, 0.5);
+    let source = GeneratorSource::new(generator, 2, false, None);
+    monitor.start_with_source(Box::new(source)).unwrap();
+    assert!(monitor.last_error().is_none());
+    monitor.stop();
+  }
+
+  // 读取几次后报告设备丢失，第一次重新打开也会失败
+  struct FlakySource {
+    inner: GeneratorSource,
+    opens: usize,
+    reads: usize,
+  }
+
+  impl FlakySource {
+    fn new() -> Self {
+      let generator = Generator::new(Signal::Sine { frequency: 1000.0 }, 44100, 0.5);
+      FlakySource {
+        inner: GeneratorSource::new(generator, 2, false, None),
+        opens: 0,
+        reads: 0,
+      }
+    }
+  }
+
+  impl CaptureSource for FlakySource {
+    fn open(&mut self) -> CaptureRes<()> {
+      self.opens += 1;
+      if self.opens == 2 {
+        return Err(CaptureError::NotOpen);
+      }
+      self.inner.open()
+    }
+
+    fn format(&self) -> StreamFormat {
+      self.inner.format()
+    }
+
+    fn read(&mut self, buffer: &mut VecDeque<u8>) -> CaptureRes<bool> {
+      self.reads += 1;
+      if self.reads == 5 {
+        return Err(CaptureError::Io(std::io::Error::other("device unplugged")));
+      }
+      self.inner.read(buffer)
+    }
+
+    fn stop(&mut self) {
+      self.inner.stop();
+    }
+
+    fn recoverable(&self, _error: &CaptureError) -> bool {
+      true
+    }
+
+    // 重新打开时原设备已不在，换成了默认设备
+    fn device_id(&self) -> Option<String> {
+      Some(
+        if self.opens == 1 {
+          "headphones"
+        } else {
+          "speakers"
+        }
+        .to_string(),
+      )
+    }
+  }
+
+  fn fast_reconnect(max_attempts: Option<u32>) -> ReconnectOptions {
+    ReconnectOptions {
+      enabled: None,
+      initial_delay: Some(1.0),
+      max_delay: Some(10.0),
+      max_attempts,
+    }
+  }
+
+  #[test]
+  fn reopens_lost_sources_with_backoff() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor.set_reconnect(fast_reconnect(None)).unwrap();
+    monitor
+      .set_reconnect_callback(Box::new(move |event| {
+        let _ = tx.send(event);
+      }))
+      .unwrap();
+    monitor
+      .start_with_source(Box::new(FlakySource::new()))
+      .unwrap();
+
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.code, ErrorCode::Io);
+    assert_eq!(event.attempts, 2);
+    assert_eq!(
+      monitor.current_device_id().unwrap().as_deref(),
+      Some("speakers")
+    );
+
+    // 重连后继续分析
+    let started = Instant::now();
+    let mut spectrum = vec![0.0; 64];
+    while spectrum.iter().all(|&v| v == 0.0) && started.elapsed() < Duration::from_secs(5) {
+      thread::sleep(Duration::from_millis(10));
+      spectrum = monitor.get_spectrum(64).unwrap();
+    }
+    assert!(monitor.running());
+    assert!(monitor.last_error().is_none());
+    monitor.stop();
+    assert!(spectrum.iter().any(|&v| v > 0.0));
+  }
+
+  // 读取时 panic 的捕获源
+  struct PanickingSource(GeneratorSource);
+
+  impl CaptureSource for PanickingSource {
+    fn open(&mut self) -> CaptureRes<()> {
+      self.0.open()
+    }
+
+    fn format(&self) -> StreamFormat {
+      self.0.format()
+    }
+
+    fn read(&mut self, _buffer: &mut VecDeque<u8>) -> CaptureRes<bool> {
+      panic!("driver exploded");
+    }
+
+    fn stop(&mut self) {
+      self.0.stop();
+    }
+  }
+
+  #[test]
+  fn capture_thread_panics_are_reported() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor
+      .set_error_callback(Box::new(move |error| {
+        let _ = tx.send(error);
+      }))
+      .unwrap();
+    let generator = Generator::new(Signal::Silence, 44100, 0.5);
+    let source = PanickingSource(GeneratorSource::new(generator, 2, false, None));
+    monitor.start_with_source(Box::new(source)).unwrap();
+
+    let error = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(error.code, ErrorCode::Unknown);
+    assert!(
+      error.message.contains("driver exploded"),
+      "{}",
+      error.message
+    );
+    assert!(!monitor.running());
+    assert_eq!(monitor.last_error().unwrap().message, error.message);
+  }
+
+  // 像静音的环回设备一样不断返回，却从不产生数据
+  struct SilentSource(GeneratorSource);
+
+  impl CaptureSource for SilentSource {
+    fn open(&mut self) -> CaptureRes<()> {
+      self.0.open()
+    }
+
+    fn format(&self) -> StreamFormat {
+      self.0.format()
+    }
+
+    fn read(&mut self, _buffer: &mut VecDeque<u8>) -> CaptureRes<bool> {
+      thread::sleep(Duration::from_millis(1));
+      Ok(true)
+    }
+
+    fn stop(&mut self) {
+      self.0.stop();
+    }
+  }
+
+  #[test]
+  fn stops_while_the_source_is_silent() {
+    let mut monitor = AudioMonitor::new();
+    let generator = Generator::new(Signal::Silence, 44100, 0.5);
+    let source = SilentSource(GeneratorSource::new(generator, 2, false, None));
+    monitor.start_with_source(Box::new(source)).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert!(monitor.running());
+
+    let started = Instant::now();
+    monitor.stop();
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(!monitor.running());
+  }
+
+  #[test]
+  fn gives_up_after_max_attempts() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor.set_reconnect(fast_reconnect(Some(1))).unwrap();
+    monitor
+      .set_error_callback(Box::new(move |error| {
+        let _ = tx.send(error);
+      }))
+      .unwrap();
+    monitor
+      .start_with_source(Box::new(FlakySource::new()))
+      .unwrap();
+
+    // 唯一一次重新打开失败后报告该错误
+    let error = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(error.code, ErrorCode::Unknown);
+    assert!(!monitor.running());
+  }
+
+  // 报告一次默认输出设备切换到 `device_id`
+  fn switch_to(device_id: &str) -> Box<ScriptedNotifier> {
+    Box::new(ScriptedNotifier(
+      vec![DeviceNotification::DefaultChanged(
+        DeviceDirection::Render,
+        DeviceRole::Console,
+        Some(device_id.to_string()),
+      )]
+      .into(),
+    ))
+  }
+
+  // 报告设备ID的捕获源
+  struct NamedSource(&'static str, GeneratorSource);
+
+  impl CaptureSource for NamedSource {
+    fn open(&mut self) -> CaptureRes<()> {
+      self.1.open()
+    }
+
+    fn format(&self) -> StreamFormat {
+      self.1.format()
+    }
+
+    fn read(&mut self, buffer: &mut VecDeque<u8>) -> CaptureRes<bool> {
+      self.1.read(buffer)
+    }
+
+    fn stop(&mut self) {
+      self.1.stop();
+    }
+
+    fn device_id(&self) -> Option<String> {
+      Some(self.0.to_string())
+    }
+  }
+
+  #[test]
+  fn follower_starts_from_the_opened_device() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor
+      .set_switch_callback(Box::new(move |event| {
+        let _ = tx.send(event);
+      }))
+      .unwrap();
+    // 启动时还不知道当前设备，第一次通知报告的默认设备就是已打开的设备，之后才真正切换
+    let script = ["speakers", "headphones"].map(|device_id| {
+      DeviceNotification::DefaultChanged(
+        DeviceDirection::Render,
+        DeviceRole::Console,
+        Some(device_id.to_string()),
+      )
+    });
+    let follower = DeviceFollower::new(
+      Box::new(ScriptedNotifier(script.into())),
+      DeviceDirection::Render,
+      None,
+      Box::new(|_| tone(1000.0)),
+    );
+    let generator = Generator::new(Signal::Silence, 44100, 0.5);
+    let source = NamedSource("speakers", GeneratorSource::new(generator, 2, true, None));
+    monitor.start_following(Box::new(source), follower).unwrap();
+
+    // 第一次切换就来自已打开的设备，说明重复的通知没有引起切换
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.previous_id.as_deref(), Some("speakers"));
+    assert_eq!(event.device_id, "headphones");
+    monitor.stop();
+    assert!(rx.try_recv().is_err());
+  }
+
+  fn tone(frequency: f32) -> Box<dyn CaptureSource> {
+    let generator = Generator::new(Signal::Sine { frequency }, 44100, 0.5);
+    Box::new(GeneratorSource::new(generator, 2, true, None))
+  }
+
+  #[test]
+  fn follows_the_default_device() {
+    let (tx, rx) = mpsc::channel();
+    let mut monitor = AudioMonitor::new();
+    monitor
+      .set_switch_callback(Box::new(move |event| {
+        let _ = tx.send(event);
+      }))
+      .unwrap();
+    // 每个设备播放不同频率的正弦波
+    let follower = DeviceFollower::new(
+      switch_to("headphones"),
+      DeviceDirection::Render,
+      Some("speakers".to_string()),
+      Box::new(|device_id| {
+        tone(if device_id == "headphones" {
+          5000.0
+        } else {
+          1000.0
+        })
+      }),
+    );
+    monitor.start_following(tone(1000.0), follower).unwrap();
+
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.previous_id.as_deref(), Some("speakers"));
+    assert_eq!(event.device_id, "headphones");
+    assert_eq!(
+      monitor.current_device_id().unwrap().as_deref(),
+      Some("headphones")
+    );
+
+    // 5 kHz 落在 64 个线性频段的第 14 个频段
+    let started = Instant::now();
+    let mut peak = 0;
+    while peak != 14 && started.elapsed() < Duration::from_secs(5) {
+      thread::sleep(Duration::from_millis(10));
+      let spectrum = monitor.get_spectrum(64).unwrap();
+      peak = (0..spectrum.len())
+        .max_by(|&a, &b| spectrum[a].total_cmp(&spectrum[b]))
+        .unwrap();
     }
+    monitor.stop();
+    assert_eq!(peak, 14);
   }
 }
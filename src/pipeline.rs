@@ -0,0 +1,192 @@
+use crate::agc::AutoGain;
+use crate::downmix::{downmix, max_spectrum};
+use crate::fft::SpectrumAnalyzer;
+use crate::smoothing::BandSmoother;
+use crate::types::DownmixMode;
+
+// 轮询接口最多保留最近使用的几种频段数的状态
+const POLLED_LAYOUTS: usize = 4;
+
+/// Automatic gain and smoothing state of one consumer, so that callbacks, readers and
+/// `getSpectrum` with different band counts do not advance or reset each other.
+#[derive(Default)]
+pub struct BandState {
+  revision: u64,
+  auto_gain: Option<AutoGain>,
+  smoother: BandSmoother,
+}
+
+/// Turns the per-channel samples of a frame into band values: downmix, analysis,
+/// automatic gain and smoothing. Shared by `getSpectrum` and the spectrum callback, which
+/// keep their own [BandState].
+pub struct SpectrumPipeline {
+  pub downmix: DownmixMode,
+  pub analyzer: SpectrumAnalyzer,
+  // 各接收端的初始状态，设置变化时递增版本号使其重新复制
+  auto_gain: Option<AutoGain>,
+  smoother: BandSmoother,
+  revision: u64,
+  // `getSpectrum` 等轮询接口按频段数保存状态，最近使用的在最后
+  polled: Vec<(usize, BandState)>,
+  // 降混后的样本与合并后的频段值，重复使用以免每帧分配
+  mixed: Vec<f32>,
+  combined: Vec<f32>,
+}
+
+impl SpectrumPipeline {
+  pub fn new(fft_size: usize) -> Self {
+    SpectrumPipeline {
+      downmix: DownmixMode::Mono,
+      analyzer: SpectrumAnalyzer::new(fft_size),
+      auto_gain: None,
+      smoother: BandSmoother::default(),
+      revision: 0,
+      polled: Vec::new(),
+      mixed: Vec::new(),
+      combined: Vec::new(),
+    }
+  }
+
+  /// Automatic gain of every consumer, restarting their history.
+  pub fn set_auto_gain(&mut self, auto_gain: Option<AutoGain>) {
+    self.auto_gain = auto_gain;
+    self.revision += 1;
+  }
+
+  /// Smoothing of every consumer, restarting their values and peaks.
+  pub fn set_smoother(&mut self, smoother: BandSmoother) {
+    self.smoother = smoother;
+    self.revision += 1;
+  }
+
+  /// Band values of the combined channels before automatic gain and smoothing. All zeros
+  /// while no samples have arrived.
+  ///
+  /// The returned slice is owned by the pipeline and overwritten by the next call.
+  pub fn combined(&mut self, channels: &[Vec<f32>], num_bands: usize) -> &[f32] {
+    self.combined.clear();
+    if channels.is_empty() {
+      let len = self.analyzer.band_ranges(num_bands).len();
+      self.combined.resize(len, 0.0);
+    } else if self.downmix == DownmixMode::Max {
+      for (i, samples) in channels.iter().enumerate() {
+        let spectrum = self.analyzer.analyze(samples, num_bands);
+        if i == 0 {
+          self.combined.extend_from_slice(spectrum);
+        } else {
+          max_spectrum(&mut self.combined, spectrum);
+        }
+      }
+    } else {
+      downmix(channels, self.downmix, &mut self.mixed);
+      let spectrum = self.analyzer.analyze(&self.mixed, num_bands);
+      self.combined.extend_from_slice(spectrum);
+    }
+    &self.combined
+  }
+
+  /// Advance the automatic gain and smoother of `state` with the current frame. Their values
+  /// and peaks are the final output.
+  pub fn smoothed<'a>(
+    &mut self,
+    channels: &[Vec<f32>],
+    num_bands: usize,
+    state: &'a mut BandState,
+  ) -> &'a BandSmoother {
+    if state.revision != self.revision {
+      *state = BandState {
+        revision: self.revision,
+        auto_gain: self.auto_gain.clone(),
+        smoother: self.smoother.clone(),
+      };
+    }
+
+    let floor = self.analyzer.floor();
+    let raw = self.combined(channels, num_bands);
+    let input = match state.auto_gain.as_mut() {
+      Some(auto_gain) => auto_gain.update(raw, floor),
+      None => raw,
+    };
+    state.smoother.update(input);
+    &state.smoother
+  }
+
+  /// Same as [SpectrumPipeline::smoothed] with the state kept for `num_bands` polled bands.
+  /// Only the most recently used band counts keep their state.
+  pub fn polled(&mut self, channels: &[Vec<f32>], num_bands: usize) -> &BandSmoother {
+    let mut state = match self
+      .polled
+      .iter()
+      .position(|(bands, _)| *bands == num_bands)
+    {
+      Some(index) => self.polled.remove(index).1,
+      None => {
+        if self.polled.len() >= POLLED_LAYOUTS {
+          self.polled.remove(0);
+        }
+        BandState::default()
+      }
+    };
+    self.smoothed(channels, num_bands, &mut state);
+    self.polled.push((num_bands, state));
+    &self.polled[self.polled.len() - 1].1.smoother
+  }
+
+  /// Weighted level of the combined channels in dBFS.
+  pub fn level(&mut self, channels: &[Vec<f32>]) -> f32 {
+    if channels.is_empty() {
+      self.analyzer.level(&[])
+    } else if self.downmix == DownmixMode::Max {
+      channels
+        .iter()
+        .map(|samples| self.analyzer.level(samples))
+        .fold(f32::NEG_INFINITY, f32::max)
+    } else {
+      downmix(channels, self.downmix, &mut self.mixed);
+      self.analyzer.level(&self.mixed)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn consumers_keep_their_own_smoothing() {
+    let mut pipeline = SpectrumPipeline::new(1024);
+    pipeline.set_smoother(BandSmoother::new(10.0, 10.0, 0.0, 0.0));
+    let tone: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.3).sin()).collect();
+    let loud = vec![tone];
+    let quiet = vec![vec![0.0; 1024]];
+
+    let first = pipeline.polled(&loud, 16).values().to_vec();
+    // 其他频段数与独立的接收端不会重置或推进同一状态
+    pipeline.polled(&quiet, 8);
+    let mut state = BandState::default();
+    pipeline.smoothed(&quiet, 16, &mut state);
+    assert_eq!(state.smoother.values(), pipeline.combined(&quiet, 16));
+
+    let second = pipeline.polled(&quiet, 16).values().to_vec();
+    for (a, b) in first.iter().zip(&second) {
+      assert!((a - b).abs() < 0.01, "{} {}", a, b);
+    }
+  }
+
+  #[test]
+  fn polled_state_is_kept_for_recent_band_counts() {
+    let mut pipeline = SpectrumPipeline::new(1024);
+    let silence = vec![vec![0.0; 1024]];
+    for num_bands in 1..=100 {
+      pipeline.polled(&silence, num_bands);
+    }
+    let kept: Vec<usize> = pipeline.polled.iter().map(|(bands, _)| *bands).collect();
+    assert_eq!(kept, vec![97, 98, 99, 100]);
+
+    // 再次使用的频段数移到最后，最久未用的被丢弃
+    pipeline.polled(&silence, 98);
+    pipeline.polled(&silence, 7);
+    let kept: Vec<usize> = pipeline.polled.iter().map(|(bands, _)| *bands).collect();
+    assert_eq!(kept, vec![99, 100, 98, 7]);
+  }
+}
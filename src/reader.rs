@@ -0,0 +1,338 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use std::{mem, ptr};
+
+use crate::types::{FrameOverflow, FrameResult, SpectrumEvent};
+use napi::{sys, Env, JsObject, JsSymbol, Result};
+use napi_derive::napi;
+
+/// Bounded queue of analysed frames between the processing thread and a JS reader.
+/// When the reader falls behind, the oldest frames are dropped so the capture never blocks,
+/// unless the queue was created with [FrameOverflow::Block].
+pub struct FrameQueue {
+  state: Mutex<QueueState>,
+  // 读取端取走一帧或关闭时通知阻塞的生产者
+  space: Condvar,
+}
+
+type Waiter = Box<dyn FnOnce(Option<SpectrumEvent>) + Send>;
+
+struct QueueState {
+  frames: VecDeque<SpectrumEvent>,
+  // 等待下一帧的读取请求，只在没有缓冲帧时存在
+  waiters: VecDeque<Waiter>,
+  capacity: usize,
+  overflow: FrameOverflow,
+  closed: bool,
+}
+
+impl FrameQueue {
+  pub fn new(capacity: usize, overflow: FrameOverflow) -> Self {
+    FrameQueue {
+      state: Mutex::new(QueueState {
+        frames: VecDeque::new(),
+        waiters: VecDeque::new(),
+        capacity: capacity.max(1),
+        overflow,
+        closed: false,
+      }),
+      space: Condvar::new(),
+    }
+  }
+
+  /// Append a frame. When the queue is full, the oldest frame is dropped, or with
+  /// [FrameOverflow::Block] the reader is waited for as long as `waiting` returns true.
+  /// Returns false once the queue has been closed or waiting was given up.
+  pub fn push(&self, frame: SpectrumEvent, waiting: impl Fn() -> bool) -> bool {
+    let Ok(mut state) = self.state.lock() else {
+      return false;
+    };
+    while state.overflow == FrameOverflow::Block
+      && state.frames.len() >= state.capacity
+      && !state.closed
+    {
+      // 定期检查是否仍需等待，例如监控器停止时
+      if !waiting() {
+        return false;
+      }
+      state = match self.space.wait_timeout(state, Duration::from_millis(50)) {
+        Ok((state, _)) => state,
+        Err(_) => return false,
+      };
+    }
+    if state.closed {
+      return false;
+    }
+    if let Some(waiter) = state.waiters.pop_front() {
+      // 等待方在释放锁之后处理
+      drop(state);
+      waiter(Some(frame));
+      return true;
+    }
+    if state.frames.len() >= state.capacity {
+      state.frames.pop_front();
+    }
+    state.frames.push_back(frame);
+    true
+  }
+
+  /// Pass the next frame to `waiter`, right away if one is buffered, otherwise from the
+  /// producer once it arrives. The waiter gets `None` once the queue is closed and drained.
+  pub fn pop(&self, waiter: impl FnOnce(Option<SpectrumEvent>) + Send + 'static) {
+    let Ok(mut state) = self.state.lock() else {
+      return waiter(None);
+    };
+    if state.frames.is_empty() && !state.closed {
+      state.waiters.push_back(Box::new(waiter));
+      return;
+    }
+    let frame = state.frames.pop_front();
+    drop(state);
+    self.space.notify_all();
+    waiter(frame);
+  }
+
+  /// Stop accepting frames and end waiting readers.
+  pub fn close(&self) {
+    let waiters = match self.state.lock() {
+      Ok(mut state) => {
+        state.closed = true;
+        mem::take(&mut state.waiters)
+      }
+      Err(_) => return,
+    };
+    self.space.notify_all();
+    for waiter in waiters {
+      waiter(None);
+    }
+  }
+}
+
+/// Producer side of a [FrameQueue], closing it when dropped, e.g. when the monitor stops.
+/// A blocking queue is only waited for while `running` is set.
+pub struct FrameSender {
+  queue: Arc<FrameQueue>,
+  running: Arc<Mutex<bool>>,
+}
+
+impl FrameSender {
+  pub fn new(queue: Arc<FrameQueue>, running: Arc<Mutex<bool>>) -> Self {
+    FrameSender { queue, running }
+  }
+
+  pub fn send(&self, frame: SpectrumEvent) -> bool {
+    self.queue.push(frame, || {
+      self.running.lock().map(|running| *running).unwrap_or(false)
+    })
+  }
+}
+
+impl Drop for FrameSender {
+  fn drop(&mut self) {
+    self.queue.close();
+  }
+}
+
+/// Async iterator over analysed frames, returned by `AudioMonitor.frames()`.
+#[napi]
+pub struct FrameReader {
+  queue: Arc<FrameQueue>,
+}
+
+#[napi]
+impl FrameReader {
+  /// Resolve with the next frame, or `done` once the monitor stops or the reader is closed.
+  #[napi(ts_return_type = "Promise<FrameResult>")]
+  pub fn next(&self, env: Env) -> Result<JsObject> {
+    let (deferred, promise) = env.create_deferred()?;
+    // 由处理线程在新帧到达时兑现，不占用 libuv 线程池
+    self.queue.pop(move |frame| {
+      deferred.resolve(move |_| {
+        Ok(FrameResult {
+          done: frame.is_none(),
+          value: frame,
+        })
+      })
+    });
+    Ok(promise)
+  }
+
+  /// Stop reading, e.g. when leaving a `for await` loop early.
+  #[napi(js_name = "return", ts_return_type = "Promise<FrameResult>")]
+  pub fn close(&self, env: Env) -> Result<JsObject> {
+    self.queue.close();
+    let (deferred, promise) = env.create_deferred()?;
+    deferred.resolve(|_| {
+      Ok(FrameResult {
+        value: None,
+        done: true,
+      })
+    });
+    Ok(promise)
+  }
+}
+
+impl FrameReader {
+  /// Wrap `queue` in a JS object that can be used with `for await`.
+  pub fn create(env: Env, queue: Arc<FrameQueue>) -> Result<JsObject> {
+    let reader = FrameReader { queue }.into_instance(env)?;
+    let mut object = reader.as_object(env);
+    // `Symbol` 是函数，不能按对象类型检查
+    let symbol = env
+      .get_global()?
+      .get_named_property_unchecked::<JsObject>("Symbol")?
+      .get_named_property::<JsSymbol>("asyncIterator")?;
+    object.set_property(
+      symbol,
+      env.create_function("asyncIterator", async_iterator)?,
+    )?;
+    Ok(object)
+  }
+}
+
+// `reader[Symbol.asyncIterator]()` 返回读取器本身
+unsafe extern "C" fn async_iterator(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+) -> sys::napi_value {
+  let mut this = ptr::null_mut();
+  sys::napi_get_cb_info(
+    env,
+    info,
+    ptr::null_mut(),
+    ptr::null_mut(),
+    &mut this,
+    ptr::null_mut(),
+  );
+  this
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::mpsc;
+  use std::thread;
+  use std::time::Instant;
+
+  fn frame(sequence: i64) -> SpectrumEvent {
+    SpectrumEvent {
+      values: vec![],
+      peaks: vec![],
+      timestamp: 0.0,
+      sequence,
+    }
+  }
+
+  // 阻塞等待下一帧
+  fn next(queue: &FrameQueue) -> Option<SpectrumEvent> {
+    let (tx, rx) = mpsc::channel();
+    queue.pop(move |frame| {
+      let _ = tx.send(frame);
+    });
+    rx.recv().unwrap()
+  }
+
+  fn running_flag() -> Arc<Mutex<bool>> {
+    Arc::new(Mutex::new(true))
+  }
+
+  #[test]
+  fn full_queue_drops_the_oldest_frames() {
+    let queue = FrameQueue::new(2, FrameOverflow::DropOldest);
+    for sequence in 0..5 {
+      assert!(queue.push(frame(sequence), || true));
+    }
+    assert_eq!(next(&queue).unwrap().sequence, 3);
+    assert_eq!(next(&queue).unwrap().sequence, 4);
+  }
+
+  #[test]
+  fn blocking_queue_waits_for_the_reader() {
+    let queue = Arc::new(FrameQueue::new(2, FrameOverflow::Block));
+    let sender = FrameSender::new(Arc::clone(&queue), running_flag());
+    let producer = thread::spawn(move || {
+      for sequence in 0..6 {
+        assert!(sender.send(frame(sequence)));
+      }
+    });
+
+    // 生产者在队列满时等待，而不是丢弃帧
+    thread::sleep(Duration::from_millis(50));
+    assert!(!producer.is_finished());
+    let sequences: Vec<i64> = (0..6).map(|_| next(&queue).unwrap().sequence).collect();
+    assert_eq!(sequences, vec![0, 1, 2, 3, 4, 5]);
+    producer.join().unwrap();
+  }
+
+  #[test]
+  fn blocked_producers_give_up_when_stopped() {
+    let running = running_flag();
+    let queue = Arc::new(FrameQueue::new(1, FrameOverflow::Block));
+    let sender = FrameSender::new(Arc::clone(&queue), Arc::clone(&running));
+    assert!(sender.send(frame(0)));
+
+    let started = Instant::now();
+    let producer = thread::spawn(move || sender.send(frame(1)));
+    thread::sleep(Duration::from_millis(20));
+    *running.lock().unwrap() = false;
+    assert!(!producer.join().unwrap());
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // 关闭的队列同样结束等待
+    let queue = Arc::new(FrameQueue::new(1, FrameOverflow::Block));
+    let sender = FrameSender::new(Arc::clone(&queue), running_flag());
+    assert!(sender.send(frame(0)));
+    let producer = thread::spawn(move || sender.send(frame(1)));
+    thread::sleep(Duration::from_millis(20));
+    queue.close();
+    assert!(!producer.join().unwrap());
+  }
+
+  #[test]
+  fn waiting_readers_are_resolved_by_the_producer() {
+    let queue = FrameQueue::new(4, FrameOverflow::DropOldest);
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..2 {
+      let tx = tx.clone();
+      queue.pop(move |frame| {
+        let _ = tx.send(frame.map(|frame| frame.sequence));
+      });
+    }
+    // 读取请求不占用线程，只在新帧到达或关闭时完成
+    assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+
+    assert!(queue.push(frame(7), || true));
+    assert_eq!(rx.try_recv(), Ok(Some(7)));
+    queue.close();
+    assert_eq!(rx.try_recv(), Ok(None));
+    assert!(next(&queue).is_none());
+  }
+
+  #[test]
+  fn dropping_the_sender_ends_the_reader() {
+    let queue = Arc::new(FrameQueue::new(4, FrameOverflow::DropOldest));
+    let sender = FrameSender::new(Arc::clone(&queue), running_flag());
+    let reader = {
+      let queue = Arc::clone(&queue);
+      thread::spawn(move || {
+        let mut sequences = Vec::new();
+        while let Some(frame) = next(&queue) {
+          sequences.push(frame.sequence);
+        }
+        sequences
+      })
+    };
+
+    assert!(sender.send(frame(0)));
+    assert!(sender.send(frame(1)));
+    drop(sender);
+    assert_eq!(reader.join().unwrap(), vec![0, 1]);
+
+    // 读取端关闭后不再接收新帧
+    let queue = Arc::new(FrameQueue::new(4, FrameOverflow::DropOldest));
+    let sender = FrameSender::new(Arc::clone(&queue), running_flag());
+    queue.close();
+    assert!(!sender.send(frame(0)));
+  }
+}
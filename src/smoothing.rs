@@ -0,0 +1,244 @@
+use std::time::Instant;
+
+use crate::types::SmoothingOptions;
+use crate::utils::check_value;
+use napi::Result;
+
+/// Attack/release ballistics and peak-hold markers for band values.
+///
+/// Time constants are in seconds, so the result does not depend on how often it is updated.
+#[derive(Clone)]
+pub struct BandSmoother {
+  attack: f32,
+  release: f32,
+  hold: f32,
+  gravity: f32,
+  values: Vec<f32>,
+  peaks: Vec<f32>,
+  // 峰值保持的时长与下落速度
+  peak_age: Vec<f32>,
+  peak_velocity: Vec<f32>,
+  last_update: Option<Instant>,
+}
+
+impl Default for BandSmoother {
+  fn default() -> Self {
+    BandSmoother::new(0.0, 0.0, 0.0, 0.0)
+  }
+}
+
+impl BandSmoother {
+  /// `attack` and `release` are the time constants of rising and falling values, `hold` is how
+  /// long a peak stays in place and `gravity` how fast it then accelerates down, in units per second².
+  /// Without gravity, peaks drop to the current value as soon as the hold time is over.
+  pub fn new(attack: f32, release: f32, hold: f32, gravity: f32) -> Self {
+    BandSmoother {
+      attack: attack.max(0.0),
+      release: release.max(0.0),
+      hold: hold.max(0.0),
+      gravity: gravity.max(0.0),
+      values: Vec::new(),
+      peaks: Vec::new(),
+      peak_age: Vec::new(),
+      peak_velocity: Vec::new(),
+      last_update: None,
+    }
+  }
+
+  /// Smoother configured with `setSmoothing`, whose times are in milliseconds. All values must
+  /// be finite and at least 0.
+  pub fn from_options(options: SmoothingOptions) -> Result<Self> {
+    for (name, value) in [
+      ("attack", options.attack),
+      ("release", options.release),
+      ("peakHold", options.peak_hold),
+      ("peakGravity", options.peak_gravity),
+    ] {
+      check_value(name, value, |value| value >= 0.0, " of at least 0")?;
+    }
+
+    let seconds = |ms: Option<f64>| ms.unwrap_or(0.0) as f32 / 1000.0;
+    Ok(BandSmoother::new(
+      seconds(options.attack),
+      seconds(options.release),
+      seconds(options.peak_hold),
+      options.peak_gravity.unwrap_or(0.0) as f32,
+    ))
+  }
+
+  pub fn values(&self) -> &[f32] {
+    &self.values
+  }
+
+  pub fn peaks(&self) -> &[f32] {
+    &self.peaks
+  }
+
+  /// Advance by the wall-clock time elapsed since the previous update.
+  pub fn update(&mut self, input: &[f32]) {
+    let now = Instant::now();
+    let elapsed = self
+      .last_update
+      .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+    self.last_update = Some(now);
+    self.advance(input, elapsed);
+  }
+
+  /// Advance by `elapsed` seconds towards `input`.
+  pub fn advance(&mut self, input: &[f32], elapsed: f32) {
+    if self.values.len() != input.len() {
+      // 频段数量变化时直接从当前输入开始
+      self.values = input.to_vec();
+      self.peaks = input.to_vec();
+      self.peak_age = vec![0.0; input.len()];
+      self.peak_velocity = vec![0.0; input.len()];
+      return;
+    }
+
+    let attack = coefficient(self.attack, elapsed);
+    let release = coefficient(self.release, elapsed);
+
+    for (i, &target) in input.iter().enumerate() {
+      let value = &mut self.values[i];
+      let rate = if target > *value { attack } else { release };
+      *value = if rate >= 1.0 {
+        target
+      } else {
+        *value + (target - *value) * rate
+      };
+
+      let peak = &mut self.peaks[i];
+      if *value >= *peak {
+        *peak = *value;
+        self.peak_age[i] = 0.0;
+        self.peak_velocity[i] = 0.0;
+        continue;
+      }
+
+      self.peak_age[i] += elapsed;
+      if self.peak_age[i] > self.hold {
+        // 保持时间结束后加速下落，没有设置重力时直接回到当前值
+        let falling = (self.peak_age[i] - self.hold).min(elapsed);
+        self.peak_velocity[i] += self.gravity * falling;
+        *peak = if self.gravity > 0.0 {
+          (*peak - self.peak_velocity[i] * falling).max(*value)
+        } else {
+          *value
+        };
+      }
+    }
+  }
+}
+
+/// Fraction of the distance to the target a one-pole low-pass covers in `elapsed` seconds,
+/// 1 for a time constant of 0.
+pub(crate) fn coefficient(time_constant: f32, elapsed: f32) -> f32 {
+  if time_constant <= 0.0 {
+    1.0
+  } else {
+    1.0 - (-elapsed / time_constant).exp()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn without_time_constants_values_follow_the_input() {
+    let mut smoother = BandSmoother::default();
+    smoother.advance(&[0.0, 1.0], 0.0);
+    smoother.advance(&[1.0, 0.0], 0.016);
+    assert_eq!(smoother.values(), &[1.0, 0.0]);
+  }
+
+  #[test]
+  fn smoothing_is_frame_rate_independent() {
+    let mut fast = BandSmoother::new(0.05, 0.3, 0.0, 0.0);
+    let mut slow = BandSmoother::new(0.05, 0.3, 0.0, 0.0);
+    fast.advance(&[0.0, 1.0], 0.0);
+    slow.advance(&[0.0, 1.0], 0.0);
+
+    for _ in 0..100 {
+      fast.advance(&[1.0, 0.0], 0.001);
+    }
+    for _ in 0..5 {
+      slow.advance(&[1.0, 0.0], 0.02);
+    }
+
+    for (a, b) in fast.values().iter().zip(slow.values()) {
+      assert!((a - b).abs() < 1e-4);
+    }
+    // 0.1 秒为两倍上升时间常数，约 86%
+    assert!((fast.values()[0] - 0.8647).abs() < 1e-3);
+    // 下降更慢，约 28%
+    assert!((fast.values()[1] - 0.7165).abs() < 1e-3);
+  }
+
+  #[test]
+  fn peaks_hold_then_fall_with_gravity() {
+    let mut smoother = BandSmoother::new(0.0, 0.0, 0.5, 2.0);
+    smoother.advance(&[1.0], 0.0);
+    smoother.advance(&[0.0], 0.25);
+    assert_eq!(smoother.peaks(), &[1.0]);
+
+    // 保持 0.5 秒后开始下落，0.5 秒内下落 1/2 * 2 * 0.5² = 0.25
+    for _ in 0..75 {
+      smoother.advance(&[0.0], 0.01);
+    }
+    let peak = smoother.peaks()[0];
+    assert!((peak - 0.75).abs() < 0.02, "{}", peak);
+
+    // 下落不会低于当前值，新的更高的值会立即刷新峰值
+    for _ in 0..200 {
+      smoother.advance(&[0.2], 0.01);
+    }
+    assert_eq!(smoother.peaks(), &[0.2]);
+    smoother.advance(&[0.9], 0.01);
+    assert_eq!(smoother.peaks(), &[0.9]);
+
+    let mut smoother = BandSmoother::new(0.0, 0.0, 0.1, 0.0);
+    smoother.advance(&[1.0], 0.0);
+    smoother.advance(&[0.5], 0.05);
+    assert_eq!(smoother.peaks(), &[1.0]);
+    smoother.advance(&[0.5], 0.1);
+    assert_eq!(smoother.peaks(), &[0.5]);
+  }
+
+  fn options(attack: f64, peak_gravity: f64) -> SmoothingOptions {
+    SmoothingOptions {
+      attack: Some(attack),
+      release: Some(100.0),
+      peak_hold: None,
+      peak_gravity: Some(peak_gravity),
+    }
+  }
+
+  #[test]
+  fn rejects_invalid_smoothing() {
+    for (options, message) in [
+      (
+        options(-1.0, 0.0),
+        "attack -1 must be a finite number of at least 0",
+      ),
+      (
+        options(f64::NAN, 0.0),
+        "attack NaN must be a finite number of at least 0",
+      ),
+      (
+        options(10.0, f64::INFINITY),
+        "peakGravity inf must be a finite number of at least 0",
+      ),
+      (
+        options(10.0, -9.8),
+        "peakGravity -9.8 must be a finite number of at least 0",
+      ),
+    ] {
+      let err = BandSmoother::from_options(options).err().unwrap();
+      assert_eq!(err.reason, message);
+    }
+
+    let smoother = BandSmoother::from_options(options(0.0, 0.0)).unwrap();
+    assert_eq!((smoother.attack, smoother.release), (0.0, 0.1));
+  }
+}
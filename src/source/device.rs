@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::devices::select_device;
+use crate::source::{CaptureError, CaptureRes, CaptureSource, SampleType, StreamFormat};
+use crate::types::DeviceKind;
+use crate::utils::{get_device_by_id, SystemDevices};
+use crate::wasapi::{
+  initialize_mta, AudioCaptureClient, AudioClient, AudioSessionControl, Device, Direction,
+  DisconnectReason, EventCallbacks, EventRegistration, Handle, SampleType as WasapiSampleType,
+  ShareMode, WasapiError, WaveFormat,
+};
+use log::{debug, info, warn};
+
+/// Shared-mode WASAPI capture of an input endpoint, or loopback capture of a render endpoint.
+///
+/// Unless a format is requested, the stream uses the device mix format so that
+/// no resampling or channel conversion happens before the analysis.
+pub struct DeviceSource {
+  kind: DeviceKind,
+  device_id: Option<String>,
+  requested: Option<StreamFormat>,
+  format: StreamFormat,
+  stream: Option<DeviceStream>,
+  opened: Option<String>,
+}
+
+/// A started shared-mode capture stream, used by the device and process sources.
+pub(super) struct DeviceStream {
+  audio_client: AudioClient,
+  capture_client: AudioCaptureClient,
+  h_event: Handle,
+  // 会话通知只持有弱引用，随流一起释放
+  _callbacks: Rc<EventCallbacks>,
+  // 停止时注销会话通知，避免每次打开或重连都残留一个注册
+  session: Option<(AudioSessionControl, EventRegistration)>,
+  disconnected: Arc<Mutex<Option<DisconnectReason>>>,
+}
+
+// SAFETY: the COM objects are only created in `open`, which runs on the capture thread
+// after the source has been moved there, and they never leave that thread.
+unsafe impl Send for DeviceSource {}
+
+impl DeviceSource {
+  /// Capture the device of `kind` with the given id, falling back to the default device of
+  /// that kind. With a `requested` format, WASAPI converts the mix format to it.
+  pub fn new(kind: DeviceKind, device_id: Option<String>, requested: Option<StreamFormat>) -> Self {
+    DeviceSource {
+      kind,
+      device_id,
+      requested,
+      format: StreamFormat::new(48000, 2, 32, SampleType::Float),
+      stream: None,
+      opened: None,
+    }
+  }
+}
+
+impl CaptureSource for DeviceSource {
+  fn open(&mut self) -> CaptureRes<()> {
+    let _ = initialize_mta();
+
+    // 获取音频设备
+    let device = get_audio_device(self.kind, self.device_id.as_deref())?;
+
+    // 初始化音频客户端
+    let audio_client = device.get_iaudioclient()?;
+    let (capture_format, convert) = match &self.requested {
+      Some(requested) => (wave_format(requested), true),
+      None => (audio_client.get_mixformat()?, false),
+    };
+    let (_, min_time) = audio_client.get_periods()?;
+
+    let stream = DeviceStream::start(audio_client, &capture_format, min_time, convert, true)?;
+    self.format = StreamFormat::try_from(&capture_format)?;
+    self.stream = Some(stream);
+    self.opened = device.get_id().ok();
+    Ok(())
+  }
+
+  fn format(&self) -> StreamFormat {
+    self.format
+  }
+
+  fn read(&mut self, buffer: &mut VecDeque<u8>) -> CaptureRes<bool> {
+    let stream = self.stream.as_ref().ok_or(CaptureError::NotOpen)?;
+    stream.read(buffer)?;
+    Ok(true)
+  }
+
+  fn stop(&mut self) {
+    if let Some(stream) = self.stream.take() {
+      stream.stop();
+    }
+  }
+
+  // 设备拔出或格式变化后重新打开，找不到原设备时会使用新的默认设备
+  fn recoverable(&self, error: &CaptureError) -> bool {
+    error.device_lost()
+  }
+
+  fn device_id(&self) -> Option<String> {
+    self.opened.clone()
+  }
+}
+
+impl DeviceStream {
+  /// Initialise `audio_client` for capturing in `format` and start the stream. With `notify`,
+  /// disconnects such as a removed device are reported by [DeviceStream::read].
+  pub(super) fn start(
+    mut audio_client: AudioClient,
+    format: &WaveFormat,
+    period: i64,
+    convert: bool,
+    notify: bool,
+  ) -> CaptureRes<Self> {
+    debug!("Capture format: {:?}", format);
+    audio_client.initialize_client(
+      format,
+      period,
+      // 渲染设备以 Capture 方向初始化时使用环回模式
+      &Direction::Capture,
+      &ShareMode::Shared,
+      convert,
+    )?;
+
+    let h_event = audio_client.set_get_eventhandle()?;
+    let capture_client = audio_client.get_audiocaptureclient()?;
+
+    // 设备移除或格式变化时记录断开原因，由 `read` 报告
+    let disconnected = Arc::new(Mutex::new(None));
+    let mut callbacks = EventCallbacks::new();
+    let reason = Arc::clone(&disconnected);
+    callbacks.set_disconnected_callback(move |disconnect| {
+      warn!("Audio session disconnected: {:?}", disconnect);
+      if let Ok(mut reason) = reason.lock() {
+        *reason = Some(disconnect);
+      }
+    });
+    let callbacks = Rc::new(callbacks);
+    let session = if notify {
+      let control = audio_client.get_audiosessioncontrol()?;
+      let registration = control.register_session_notification(Rc::downgrade(&callbacks))?;
+      Some((control, registration))
+    } else {
+      None
+    };
+
+    // 开始音频流
+    audio_client.start_stream()?;
+    info!("Audio capture started");
+
+    Ok(DeviceStream {
+      audio_client,
+      capture_client,
+      h_event,
+      _callbacks: callbacks,
+      session,
+      disconnected,
+    })
+  }
+
+  /// Append the available frames to `buffer`, waiting up to 100 ms for new ones.
+  pub(super) fn read(&self, buffer: &mut VecDeque<u8>) -> CaptureRes<()> {
+    if let Some(reason) = self.disconnected.lock().ok().and_then(|mut r| r.take()) {
+      return Err(CaptureError::Disconnected(reason));
+    }
+
+    // 从设备读取数据到队列
+    self.capture_client.read_from_device_to_deque(buffer)?;
+
+    // 等待事件或超时
+    let _ = self.h_event.wait_for_event(100);
+    Ok(())
+  }
+
+  pub(super) fn stop(self) {
+    // 停止音频流
+    let _ = self.audio_client.stop_stream();
+    if let Some((control, registration)) = self.session {
+      let _ = control.unregister_session_notification(registration);
+    }
+    info!("Audio capture stopped");
+  }
+}
+
+/// WASAPI description of a requested stream format.
+pub(super) fn wave_format(format: &StreamFormat) -> WaveFormat {
+  let sample_type = match format.sample_type {
+    SampleType::Float => WasapiSampleType::Float,
+    SampleType::Int => WasapiSampleType::Int,
+  };
+  WaveFormat::new(
+    format.bits_per_sample as usize,
+    format.valid_bits as usize,
+    &sample_type,
+    format.sample_rate as usize,
+    format.channels as usize,
+    None,
+  )
+}
+
+// 获取音频设备，优先使用指定ID的设备，如果不存在则使用默认设备
+fn get_audio_device(kind: DeviceKind, device_id: Option<&str>) -> CaptureRes<Device> {
+  let id = select_device(&SystemDevices, kind, device_id)
+    .ok_or_else(|| WasapiError::DeviceNotFound(device_id.unwrap_or("default").to_string()))?;
+  if device_id != Some(id.as_str()) {
+    debug!("Device {:?} not available, using {}", device_id, id);
+  }
+  get_device_by_id(&Direction::from(kind), &id)?
+    .ok_or_else(|| WasapiError::DeviceNotFound(id).into())
+}
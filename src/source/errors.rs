@@ -0,0 +1,119 @@
+use crate::types::ErrorCode;
+#[cfg(windows)]
+use crate::wasapi::{DisconnectReason, WasapiError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+  #[error("Capture source has not been opened")]
+  NotOpen,
+  #[error("Invalid wav file: {0}")]
+  InvalidWav(String),
+  #[error("Unsupported sample format: {0}")]
+  UnsupportedFormat(String),
+  #[error("I/O error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Capture thread panicked: {0}")]
+  Panicked(String),
+  #[cfg(windows)]
+  #[error("WASAPI error: {0}")]
+  Wasapi(#[from] WasapiError),
+  #[cfg(windows)]
+  #[error("Audio session disconnected: {0:?}")]
+  Disconnected(DisconnectReason),
+}
+
+// 常见的 Windows 音频错误码
+const E_ACCESSDENIED: u32 = 0x8007_0005;
+const E_NOTFOUND: u32 = 0x8007_0490;
+const AUDCLNT_E_DEVICE_INVALIDATED: u32 = 0x8889_0004;
+const AUDCLNT_E_UNSUPPORTED_FORMAT: u32 = 0x8889_0008;
+const AUDCLNT_E_DEVICE_IN_USE: u32 = 0x8889_000A;
+const AUDCLNT_E_SERVICE_NOT_RUNNING: u32 = 0x8889_0010;
+const AUDCLNT_E_RESOURCES_INVALIDATED: u32 = 0x8889_0026;
+
+impl CaptureError {
+  /// Category reported to JavaScript.
+  pub fn code(&self) -> ErrorCode {
+    if let Some(hresult) = self.hresult() {
+      return hresult_code(hresult);
+    }
+    match self {
+      CaptureError::NotOpen => ErrorCode::Unknown,
+      CaptureError::InvalidWav(_) => ErrorCode::InvalidFile,
+      CaptureError::UnsupportedFormat(_) => ErrorCode::UnsupportedFormat,
+      CaptureError::Io(_) => ErrorCode::Io,
+      CaptureError::Panicked(_) => ErrorCode::Unknown,
+      #[cfg(windows)]
+      CaptureError::Wasapi(err) => match err {
+        WasapiError::DeviceNotFound(_) => ErrorCode::DeviceNotFound,
+        WasapiError::UnsupportedFormat | WasapiError::UnsupportedSubformat(_) => {
+          ErrorCode::UnsupportedFormat
+        }
+        WasapiError::EventTimeout => ErrorCode::Timeout,
+        _ => ErrorCode::Unknown,
+      },
+      #[cfg(windows)]
+      CaptureError::Disconnected(reason) => match reason {
+        DisconnectReason::ServerShutdown => ErrorCode::ServiceNotRunning,
+        DisconnectReason::ExclusiveModeOverride => ErrorCode::DeviceInUse,
+        _ => ErrorCode::DeviceInvalidated,
+      },
+    }
+  }
+
+  /// Whether the device may become available again, so that reopening it is worth a try.
+  pub fn device_lost(&self) -> bool {
+    matches!(
+      self.code(),
+      ErrorCode::DeviceNotFound
+        | ErrorCode::DeviceInvalidated
+        | ErrorCode::DeviceInUse
+        | ErrorCode::ServiceNotRunning
+    )
+  }
+
+  /// HRESULT of the failing Windows call, if any.
+  pub fn hresult(&self) -> Option<i32> {
+    match self {
+      #[cfg(windows)]
+      CaptureError::Wasapi(WasapiError::Windows(err))
+      | CaptureError::Wasapi(WasapiError::RegisterNotifications(err)) => Some(err.code().0),
+      _ => None,
+    }
+  }
+}
+
+// 按 HRESULT 归类失败的 Windows 调用
+fn hresult_code(hresult: i32) -> ErrorCode {
+  match hresult as u32 {
+    E_ACCESSDENIED => ErrorCode::AccessDenied,
+    E_NOTFOUND => ErrorCode::DeviceNotFound,
+    AUDCLNT_E_DEVICE_INVALIDATED | AUDCLNT_E_RESOURCES_INVALIDATED => ErrorCode::DeviceInvalidated,
+    AUDCLNT_E_UNSUPPORTED_FORMAT => ErrorCode::UnsupportedFormat,
+    AUDCLNT_E_DEVICE_IN_USE => ErrorCode::DeviceInUse,
+    AUDCLNT_E_SERVICE_NOT_RUNNING => ErrorCode::ServiceNotRunning,
+    _ => ErrorCode::Unknown,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn maps_audio_client_errors() {
+    assert_eq!(
+      hresult_code(0x8889_0004_u32 as i32),
+      ErrorCode::DeviceInvalidated
+    );
+    assert_eq!(hresult_code(0x8889_000A_u32 as i32), ErrorCode::DeviceInUse);
+    assert_eq!(
+      hresult_code(0x8007_0005_u32 as i32),
+      ErrorCode::AccessDenied
+    );
+    assert_eq!(hresult_code(0), ErrorCode::Unknown);
+    let error = CaptureError::InvalidWav("no data chunk found".to_string());
+    assert_eq!(error.code(), ErrorCode::InvalidFile);
+    assert!(!error.device_lost());
+  }
+}
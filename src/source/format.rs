@@ -0,0 +1,231 @@
+#[cfg(windows)]
+use crate::source::CaptureError;
+use crate::source::PcmDecoder;
+use crate::types::CaptureFormat;
+use crate::utils::channel_count;
+#[cfg(windows)]
+use crate::wasapi::{SampleType as WasapiSampleType, WaveFormat};
+use napi::{Error, Status};
+use napi_derive::napi;
+use std::fmt;
+
+// 声道掩码中每一位对应的扬声器位置，顺序与 ksmedia.h 中的 SPEAKER_XXX 定义一致
+const SPEAKER_NAMES: [&str; 18] = [
+  "FrontLeft",
+  "FrontRight",
+  "FrontCenter",
+  "LowFrequency",
+  "BackLeft",
+  "BackRight",
+  "FrontLeftOfCenter",
+  "FrontRightOfCenter",
+  "BackCenter",
+  "SideLeft",
+  "SideRight",
+  "TopCenter",
+  "TopFrontLeft",
+  "TopFrontCenter",
+  "TopFrontRight",
+  "TopBackLeft",
+  "TopBackCenter",
+  "TopBackRight",
+];
+
+/// Sample type, float or integer
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, Eq, PartialEq)]
+pub enum SampleType {
+  Float,
+  Int,
+}
+
+impl fmt::Display for SampleType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match *self {
+      SampleType::Float => write!(f, "Float"),
+      SampleType::Int => write!(f, "Int"),
+    }
+  }
+}
+
+/// Platform independent description of the interleaved frames delivered by a [CaptureSource](super::CaptureSource).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StreamFormat {
+  pub sample_rate: u32,
+  pub channels: u16,
+  pub bits_per_sample: u16,
+  pub valid_bits: u16,
+  pub sample_type: SampleType,
+  pub channel_mask: u32,
+}
+
+impl StreamFormat {
+  /// Build a format where every sample uses all of its bits, with a simple channel mask.
+  pub fn new(
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+    sample_type: SampleType,
+  ) -> Self {
+    let channel_mask = match channels {
+      1..=18 => (1 << channels) - 1,
+      _ => 0,
+    };
+    StreamFormat {
+      sample_rate,
+      channels,
+      bits_per_sample,
+      valid_bits: bits_per_sample,
+      sample_type,
+      channel_mask,
+    }
+  }
+
+  /// Format requested with `setCaptureFormat`. Formats the decoder cannot read are rejected
+  /// here rather than when the capture thread opens the stream.
+  pub fn from_options(format: CaptureFormat) -> napi::Result<Self> {
+    let sample_rate = format.sample_rate.unwrap_or(48000);
+    if sample_rate == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "Sample rate must be positive".to_string(),
+      ));
+    }
+    let sample_type = format.sample_type.unwrap_or(SampleType::Float);
+    let default_bits = match sample_type {
+      SampleType::Float => 32,
+      SampleType::Int => 16,
+    };
+    let format = StreamFormat::new(
+      sample_rate,
+      channel_count(format.channels.unwrap_or(2))?,
+      // 超出范围的位数与其他不支持的位数一样被解码器拒绝
+      u16::try_from(format.bits_per_sample.unwrap_or(default_bits)).unwrap_or(0),
+      sample_type,
+    );
+    PcmDecoder::new(&format).map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+    Ok(format)
+  }
+
+  /// Number of bytes in one frame, i.e. one sample for every channel.
+  pub fn block_align(&self) -> usize {
+    self.channels as usize * self.bits_per_sample as usize / 8
+  }
+
+  /// Speaker position of every channel, taken from the channel mask in channel order.
+  /// Channels beyond the positions present in the mask are named by their index.
+  pub fn channel_names(&self) -> Vec<String> {
+    let mut names: Vec<String> = SPEAKER_NAMES
+      .iter()
+      .enumerate()
+      .filter(|(bit, _)| self.channel_mask & (1 << bit) != 0)
+      .map(|(_, name)| name.to_string())
+      .take(self.channels as usize)
+      .collect();
+    for channel in names.len()..self.channels as usize {
+      names.push(format!("Channel{}", channel));
+    }
+    names
+  }
+}
+
+/// Describe the frames of a WASAPI stream.
+#[cfg(windows)]
+impl TryFrom<&WaveFormat> for StreamFormat {
+  type Error = CaptureError;
+
+  fn try_from(wave_format: &WaveFormat) -> Result<Self, Self::Error> {
+    let sample_type = match wave_format.get_subformat()? {
+      WasapiSampleType::Float => SampleType::Float,
+      WasapiSampleType::Int => SampleType::Int,
+    };
+    Ok(StreamFormat {
+      sample_rate: wave_format.get_samplespersec(),
+      channels: wave_format.get_nchannels(),
+      bits_per_sample: wave_format.get_bitspersample(),
+      valid_bits: wave_format.get_validbitspersample(),
+      sample_type,
+      channel_mask: wave_format.get_dwchannelmask(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn capture_format(
+    sample_rate: u32,
+    channels: u32,
+    sample_type: SampleType,
+    bits: u32,
+  ) -> CaptureFormat {
+    CaptureFormat {
+      sample_rate: Some(sample_rate),
+      channels: Some(channels),
+      sample_type: Some(sample_type),
+      bits_per_sample: Some(bits),
+    }
+  }
+
+  #[test]
+  fn rejects_unsupported_capture_formats() {
+    use SampleType::{Float, Int};
+    for (format, message) in [
+      (
+        capture_format(48000, 2, Int, 12),
+        "Unsupported sample format: Int samples with 12 bits",
+      ),
+      (
+        capture_format(48000, 2, Float, 16),
+        "Unsupported sample format: Float samples with 16 bits",
+      ),
+      (
+        capture_format(48000, 2, Int, 65552),
+        "Unsupported sample format: Int samples with 0 bits",
+      ),
+      (
+        capture_format(48000, 0, Float, 32),
+        "Channel count 0 must be between 1 and 65535",
+      ),
+      (
+        capture_format(48000, 65536, Float, 32),
+        "Channel count 65536 must be between 1 and 65535",
+      ),
+      (
+        capture_format(0, 2, Float, 32),
+        "Sample rate must be positive",
+      ),
+    ] {
+      let err = StreamFormat::from_options(format).unwrap_err();
+      assert_eq!(err.status, Status::InvalidArg);
+      assert_eq!(err.reason, message);
+    }
+
+    for (sample_type, bits) in [
+      (Int, 8),
+      (Int, 16),
+      (Int, 24),
+      (Int, 32),
+      (Float, 32),
+      (Float, 64),
+    ] {
+      let format = StreamFormat::from_options(capture_format(44100, 6, sample_type, bits));
+      assert_eq!(
+        format.unwrap(),
+        StreamFormat::new(44100, 6, bits as u16, sample_type)
+      );
+    }
+
+    let defaults = CaptureFormat {
+      sample_rate: None,
+      channels: None,
+      sample_type: Some(Int),
+      bits_per_sample: None,
+    };
+    assert_eq!(
+      StreamFormat::from_options(defaults).unwrap(),
+      StreamFormat::new(48000, 2, 16, Int)
+    );
+  }
+}
@@ -0,0 +1,493 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use crate::source::pacing::Pacer;
+use crate::source::{CaptureRes, CaptureSource, SampleType, StreamFormat, READ_BLOCK_MS};
+use crate::types::{GeneratorOptions, SignalKind};
+use crate::utils::{channel_count, check_value};
+use log::info;
+use napi::{Error, Result, Status};
+
+const DEFAULT_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Waveform produced by a [Generator].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Signal {
+  Sine {
+    frequency: f32,
+  },
+  /// Sum of several sines, scaled so that the peak never exceeds the amplitude.
+  MultiTone {
+    frequencies: Vec<f32>,
+  },
+  Square {
+    frequency: f32,
+  },
+  Saw {
+    frequency: f32,
+  },
+  WhiteNoise,
+  PinkNoise,
+  /// Exponential sweep from `start` to `end` Hz over `period` seconds, then restarting.
+  Sweep {
+    start: f32,
+    end: f32,
+    period: f32,
+  },
+  /// One sample at the configured amplitude every `period` seconds.
+  Impulse {
+    period: f32,
+  },
+  Silence,
+}
+
+/// Deterministic test signal generator producing samples in the -1.0 ~ 1.0 range.
+pub struct Generator {
+  signal: Signal,
+  sample_rate: u32,
+  amplitude: f32,
+  position: u64,
+  rng: u64,
+  pink: [f32; 7],
+}
+
+impl Generator {
+  pub fn new(signal: Signal, sample_rate: u32, amplitude: f32) -> Self {
+    Generator {
+      signal,
+      sample_rate: sample_rate.max(1),
+      amplitude,
+      position: 0,
+      rng: DEFAULT_SEED,
+      pink: [0.0; 7],
+    }
+  }
+
+  pub fn sample_rate(&self) -> u32 {
+    self.sample_rate
+  }
+
+  /// Produce the next sample.
+  pub fn next_sample(&mut self) -> f32 {
+    let t = self.position as f64 / self.sample_rate as f64;
+    let value = match &self.signal {
+      Signal::Sine { frequency } => (2.0 * PI * *frequency as f64 * t).sin() as f32,
+      Signal::MultiTone { frequencies } => {
+        if frequencies.is_empty() {
+          0.0
+        } else {
+          let sum: f64 = frequencies
+            .iter()
+            .map(|f| (2.0 * PI * *f as f64 * t).sin())
+            .sum();
+          (sum / frequencies.len() as f64) as f32
+        }
+      }
+      Signal::Square { frequency } => {
+        if (*frequency as f64 * t).fract() < 0.5 {
+          1.0
+        } else {
+          -1.0
+        }
+      }
+      Signal::Saw { frequency } => (2.0 * (*frequency as f64 * t).fract() - 1.0) as f32,
+      Signal::WhiteNoise => self.next_white(),
+      Signal::PinkNoise => self.next_pink(),
+      Signal::Sweep { start, end, period } => {
+        let period = (*period as f64).max(1.0 / self.sample_rate as f64);
+        let (start, end) = (start.max(1.0) as f64, end.max(1.0) as f64);
+        let t = t % period;
+        let ratio = end / start;
+        // 指数扫频的解析相位，保证扫频过程中波形连续
+        let phase = if (ratio - 1.0).abs() < 1e-9 {
+          2.0 * PI * start * t
+        } else {
+          2.0 * PI * start * period / ratio.ln() * (ratio.powf(t / period) - 1.0)
+        };
+        phase.sin() as f32
+      }
+      Signal::Impulse { period } => {
+        let interval = ((*period as f64 * self.sample_rate as f64).round() as u64).max(1);
+        if self.position.is_multiple_of(interval) {
+          1.0
+        } else {
+          0.0
+        }
+      }
+      Signal::Silence => 0.0,
+    };
+    self.position += 1;
+    value * self.amplitude
+  }
+
+  /// Fill `out` with consecutive samples.
+  pub fn fill(&mut self, out: &mut [f32]) {
+    for sample in out.iter_mut() {
+      *sample = self.next_sample();
+    }
+  }
+
+  // xorshift64*，足够用于测试信号，且结果可复现
+  fn next_white(&mut self) -> f32 {
+    self.rng ^= self.rng >> 12;
+    self.rng ^= self.rng << 25;
+    self.rng ^= self.rng >> 27;
+    let bits = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 40;
+    (bits as f32 / (1u64 << 24) as f32) * 2.0 - 1.0
+  }
+
+  // Paul Kellett 的粉红噪声滤波器
+  fn next_pink(&mut self) -> f32 {
+    let white = self.next_white();
+    let b = &mut self.pink;
+    b[0] = 0.99886 * b[0] + white * 0.055_517_9;
+    b[1] = 0.99332 * b[1] + white * 0.075_075_9;
+    b[2] = 0.96900 * b[2] + white * 0.153_852;
+    b[3] = 0.86650 * b[3] + white * 0.310_485_6;
+    b[4] = 0.55000 * b[4] + white * 0.532_952_2;
+    b[5] = -0.7616 * b[5] - white * 0.016_898;
+    let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+    b[6] = white * 0.115926;
+    (pink * 0.11).clamp(-1.0, 1.0)
+  }
+}
+
+/// Feeds the output of a [Generator] to every channel of a 32 bit float stream.
+pub struct GeneratorSource {
+  generator: Generator,
+  channels: u16,
+  realtime: bool,
+  remaining: Option<u64>,
+  pacer: Pacer,
+  block: Vec<f32>,
+}
+
+impl GeneratorSource {
+  /// `duration` limits the source to the given number of seconds, otherwise it never ends.
+  pub fn new(generator: Generator, channels: u16, realtime: bool, duration: Option<f64>) -> Self {
+    let sample_rate = generator.sample_rate();
+    GeneratorSource {
+      generator,
+      channels: channels.max(1),
+      realtime,
+      remaining: duration.map(|d| (d.max(0.0) * sample_rate as f64).round() as u64),
+      pacer: Pacer::new(sample_rate),
+      block: Vec::new(),
+    }
+  }
+}
+
+impl CaptureSource for GeneratorSource {
+  fn open(&mut self) -> CaptureRes<()> {
+    self.pacer = Pacer::new(self.generator.sample_rate());
+    info!("Signal generator started");
+    Ok(())
+  }
+
+  fn format(&self) -> StreamFormat {
+    StreamFormat::new(
+      self.generator.sample_rate(),
+      self.channels,
+      32,
+      SampleType::Float,
+    )
+  }
+
+  fn read(&mut self, buffer: &mut VecDeque<u8>) -> CaptureRes<bool> {
+    let mut frames = (self.generator.sample_rate() as u64 * READ_BLOCK_MS / 1000).max(1);
+    if let Some(remaining) = self.remaining.as_mut() {
+      frames = frames.min(*remaining);
+      *remaining -= frames;
+    }
+    if frames == 0 {
+      return Ok(false);
+    }
+
+    if self.realtime {
+      self.pacer.wait();
+    }
+
+    self.block.resize(frames as usize, 0.0);
+    self.generator.fill(&mut self.block);
+    for sample in self.block.iter() {
+      for _ in 0..self.channels {
+        buffer.extend(sample.to_le_bytes());
+      }
+    }
+    self.pacer.advance(frames as usize);
+    Ok(true)
+  }
+
+  fn stop(&mut self) {
+    info!("Signal generator stopped");
+  }
+}
+
+/// Generator source chosen with `setGenerator`, kept so that each start begins a fresh signal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorConfig {
+  pub signal: Signal,
+  pub sample_rate: u32,
+  pub amplitude: f32,
+  pub channels: u16,
+  pub duration: Option<f64>,
+  pub realtime: bool,
+}
+
+impl GeneratorConfig {
+  /// Check the `setGenerator` options and fill in the defaults.
+  pub fn from_options(options: GeneratorOptions) -> Result<Self> {
+    let sample_rate = options.sample_rate.unwrap_or(44100);
+    if sample_rate == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "Sample rate must be positive".to_string(),
+      ));
+    }
+    let channels = channel_count(options.channels.unwrap_or(2))?;
+
+    let at_least_zero = |value: f64| value >= 0.0;
+    check_value(
+      "frequency",
+      options.frequency,
+      at_least_zero,
+      " of at least 0",
+    )?;
+    for &frequency in options.frequencies.iter().flatten() {
+      check_value(
+        "frequency",
+        Some(frequency),
+        at_least_zero,
+        " of at least 0",
+      )?;
+    }
+    check_value(
+      "endFrequency",
+      options.end_frequency,
+      at_least_zero,
+      " of at least 0",
+    )?;
+    check_value("period", options.period, |period| period > 0.0, " above 0")?;
+    check_value(
+      "amplitude",
+      options.amplitude,
+      at_least_zero,
+      " of at least 0",
+    )?;
+    check_value(
+      "duration",
+      options.duration,
+      at_least_zero,
+      " of at least 0",
+    )?;
+
+    let frequency = options.frequency.unwrap_or(1000.0) as f32;
+    let period = options.period.unwrap_or(1.0) as f32;
+    let signal = match options.signal {
+      SignalKind::Sine => Signal::Sine { frequency },
+      SignalKind::MultiTone => Signal::MultiTone {
+        frequencies: options
+          .frequencies
+          .unwrap_or_else(|| vec![frequency as f64])
+          .into_iter()
+          .map(|f| f as f32)
+          .collect(),
+      },
+      SignalKind::Square => Signal::Square { frequency },
+      SignalKind::Saw => Signal::Saw { frequency },
+      SignalKind::WhiteNoise => Signal::WhiteNoise,
+      SignalKind::PinkNoise => Signal::PinkNoise,
+      SignalKind::Sweep => Signal::Sweep {
+        start: options.frequency.unwrap_or(20.0) as f32,
+        end: options.end_frequency.unwrap_or(20000.0) as f32,
+        period,
+      },
+      SignalKind::Impulse => Signal::Impulse { period },
+      SignalKind::Silence => Signal::Silence,
+    };
+
+    Ok(GeneratorConfig {
+      signal,
+      sample_rate,
+      amplitude: options.amplitude.unwrap_or(0.5) as f32,
+      channels,
+      duration: options.duration,
+      realtime: options.realtime.unwrap_or(true),
+    })
+  }
+
+  /// A source producing the signal from its start.
+  pub fn source(&self) -> GeneratorSource {
+    let generator = Generator::new(self.signal.clone(), self.sample_rate, self.amplitude);
+    GeneratorSource::new(generator, self.channels, self.realtime, self.duration)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn generate(signal: Signal, len: usize) -> Vec<f32> {
+    let mut generator = Generator::new(signal, 48000, 1.0);
+    let mut out = vec![0.0; len];
+    generator.fill(&mut out);
+    out
+  }
+
+  #[test]
+  fn waveforms_stay_in_range() {
+    let signals = [
+      Signal::Sine { frequency: 440.0 },
+      Signal::MultiTone {
+        frequencies: vec![100.0, 1000.0, 5000.0],
+      },
+      Signal::Square { frequency: 100.0 },
+      Signal::Saw { frequency: 100.0 },
+      Signal::WhiteNoise,
+      Signal::PinkNoise,
+      Signal::Sweep {
+        start: 20.0,
+        end: 20000.0,
+        period: 0.1,
+      },
+    ];
+    for signal in signals {
+      let samples = generate(signal.clone(), 9600);
+      assert!(
+        samples.iter().all(|s| (-1.0..=1.0).contains(s)),
+        "{:?}",
+        signal
+      );
+      assert!(samples.iter().any(|s| s.abs() > 0.1), "{:?}", signal);
+    }
+  }
+
+  #[test]
+  fn impulses_and_silence() {
+    let samples = generate(Signal::Impulse { period: 0.01 }, 1000);
+    let peaks: Vec<usize> = (0..samples.len()).filter(|&i| samples[i] == 1.0).collect();
+    assert_eq!(peaks, vec![0, 480, 960]);
+    assert_eq!(samples.iter().filter(|&&s| s != 0.0).count(), 3);
+
+    assert!(generate(Signal::Silence, 100).iter().all(|&s| s == 0.0));
+  }
+
+  #[test]
+  fn noise_is_deterministic() {
+    assert_eq!(
+      generate(Signal::WhiteNoise, 256),
+      generate(Signal::WhiteNoise, 256)
+    );
+  }
+
+  #[test]
+  fn source_honours_duration_and_channels() {
+    let generator = Generator::new(Signal::Sine { frequency: 1000.0 }, 1000, 0.5);
+    let mut source = GeneratorSource::new(generator, 3, false, Some(0.025));
+    source.open().unwrap();
+
+    let mut buffer = VecDeque::new();
+    while source.read(&mut buffer).unwrap() {}
+    assert_eq!(source.format().block_align(), 12);
+    assert_eq!(buffer.len(), 25 * 12);
+  }
+
+  fn options(signal: SignalKind) -> GeneratorOptions {
+    GeneratorOptions {
+      signal,
+      frequency: None,
+      frequencies: None,
+      end_frequency: None,
+      period: None,
+      amplitude: None,
+      sample_rate: None,
+      channels: None,
+      duration: None,
+      realtime: Some(false),
+    }
+  }
+
+  #[test]
+  fn checks_generator_options() {
+    let invalid = [
+      (
+        GeneratorOptions {
+          sample_rate: Some(0),
+          ..options(SignalKind::Silence)
+        },
+        "Sample rate must be positive",
+      ),
+      (
+        GeneratorOptions {
+          channels: Some(65536),
+          ..options(SignalKind::Silence)
+        },
+        "Channel count 65536 must be between 1 and 65535",
+      ),
+      (
+        GeneratorOptions {
+          frequency: Some(-440.0),
+          ..options(SignalKind::Sine)
+        },
+        "frequency -440 must be a finite number of at least 0",
+      ),
+      (
+        GeneratorOptions {
+          frequencies: Some(vec![100.0, f64::NAN]),
+          ..options(SignalKind::MultiTone)
+        },
+        "frequency NaN must be a finite number of at least 0",
+      ),
+      (
+        GeneratorOptions {
+          end_frequency: Some(f64::INFINITY),
+          ..options(SignalKind::Sweep)
+        },
+        "endFrequency inf must be a finite number of at least 0",
+      ),
+      (
+        GeneratorOptions {
+          period: Some(0.0),
+          ..options(SignalKind::Impulse)
+        },
+        "period 0 must be a finite number above 0",
+      ),
+      (
+        GeneratorOptions {
+          amplitude: Some(-0.5),
+          ..options(SignalKind::Sine)
+        },
+        "amplitude -0.5 must be a finite number of at least 0",
+      ),
+      (
+        GeneratorOptions {
+          duration: Some(f64::NAN),
+          ..options(SignalKind::Sine)
+        },
+        "duration NaN must be a finite number of at least 0",
+      ),
+    ];
+    for (options, message) in invalid {
+      let err = GeneratorConfig::from_options(options).unwrap_err();
+      assert_eq!(err.reason, message);
+    }
+
+    let config = GeneratorConfig::from_options(GeneratorOptions {
+      frequency: Some(0.0),
+      end_frequency: Some(100.0),
+      amplitude: Some(0.0),
+      channels: Some(65535),
+      ..options(SignalKind::Sweep)
+    })
+    .unwrap();
+    assert_eq!(
+      config.signal,
+      Signal::Sweep {
+        start: 0.0,
+        end: 100.0,
+        period: 1.0,
+      }
+    );
+    assert_eq!((config.sample_rate, config.channels), (44100, 65535));
+  }
+}
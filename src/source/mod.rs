@@ -0,0 +1,58 @@
+#[cfg(windows)]
+mod device;
+mod errors;
+mod format;
+mod generator;
+mod pacing;
+mod pcm;
+#[cfg(windows)]
+mod process;
+mod wav;
+
+use std::collections::VecDeque;
+
+#[cfg(windows)]
+pub use device::DeviceSource;
+pub use errors::CaptureError;
+pub use format::{SampleType, StreamFormat};
+pub use generator::{Generator, GeneratorConfig, GeneratorSource, Signal};
+pub use pcm::PcmDecoder;
+#[cfg(windows)]
+pub use process::ProcessSource;
+pub use wav::WavSource;
+
+pub type CaptureRes<T> = Result<T, CaptureError>;
+
+// 文件与合成数据源每次读取的时长（毫秒）
+pub(crate) const READ_BLOCK_MS: u64 = 10;
+
+/// A stream of interleaved audio frames that can feed an [AudioMonitor](crate::monitor::AudioMonitor).
+///
+/// Sources are moved to the capture thread before [CaptureSource::open] is called,
+/// so any platform handles should be created in `open` rather than in the constructor.
+pub trait CaptureSource: Send {
+  /// Acquire the underlying stream and start delivering data.
+  fn open(&mut self) -> CaptureRes<()>;
+
+  /// Describe the frames produced by [CaptureSource::read]. Only valid after a successful `open`.
+  fn format(&self) -> StreamFormat;
+
+  /// Append the raw bytes of any available frames to `buffer`, waiting briefly when none are ready.
+  /// Returns `false` once the source is exhausted and will not produce more data.
+  fn read(&mut self, buffer: &mut VecDeque<u8>) -> CaptureRes<bool>;
+
+  /// Stop the stream and release the underlying resources.
+  fn stop(&mut self);
+
+  /// Whether the stream can be reopened after `error`, e.g. when the device was unplugged.
+  /// The monitor then calls [CaptureSource::stop] and [CaptureSource::open] again.
+  fn recoverable(&self, _error: &CaptureError) -> bool {
+    false
+  }
+
+  /// Id of the device opened by the last successful [CaptureSource::open], which may be a
+  /// fallback when the requested device is gone. `None` for sources that are not a device.
+  fn device_id(&self) -> Option<String> {
+    None
+  }
+}
@@ -0,0 +1,33 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Keeps a file or synthetic source in step with the wall clock.
+pub(crate) struct Pacer {
+  sample_rate: u64,
+  started: Instant,
+  frames: u64,
+}
+
+impl Pacer {
+  pub fn new(sample_rate: u32) -> Self {
+    Pacer {
+      sample_rate: sample_rate.max(1) as u64,
+      started: Instant::now(),
+      frames: 0,
+    }
+  }
+
+  /// Sleep until every frame delivered so far is due.
+  pub fn wait(&self) {
+    let due = Duration::from_micros(self.frames * 1_000_000 / self.sample_rate);
+    let elapsed = self.started.elapsed();
+    if due > elapsed {
+      thread::sleep(due - elapsed);
+    }
+  }
+
+  /// Record that `frames` more frames have been delivered.
+  pub fn advance(&mut self, frames: usize) {
+    self.frames += frames as u64;
+  }
+}
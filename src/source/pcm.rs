@@ -0,0 +1,223 @@
+use crate::source::{CaptureError, CaptureRes, SampleType, StreamFormat};
+
+// 样本在容器中的存储方式
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Encoding {
+  /// 8 bit unsigned, 128 is silence
+  U8,
+  I16,
+  I24,
+  I32,
+  F32,
+  F64,
+}
+
+/// Converts interleaved little-endian PCM or IEEE float frames to normalised f32 samples.
+///
+/// Integer samples are scaled to -1.0 ~ 1.0. When fewer valid bits than container bits are
+/// declared (e.g. 24 valid bits in a 32 bit container), the samples are taken as left-justified
+/// and the unused low bits are ignored.
+#[derive(Clone, Copy, Debug)]
+pub struct PcmDecoder {
+  encoding: Encoding,
+  channels: usize,
+  sample_bytes: usize,
+  block_align: usize,
+  valid_mask: i32,
+}
+
+impl PcmDecoder {
+  pub fn new(format: &StreamFormat) -> CaptureRes<Self> {
+    let bits = format.bits_per_sample;
+    let valid_bits = match format.valid_bits {
+      0 => bits,
+      valid => valid,
+    };
+    let encoding = match (format.sample_type, bits) {
+      (SampleType::Int, 8) => Encoding::U8,
+      (SampleType::Int, 16) => Encoding::I16,
+      (SampleType::Int, 24) => Encoding::I24,
+      (SampleType::Int, 32) => Encoding::I32,
+      (SampleType::Float, 32) => Encoding::F32,
+      (SampleType::Float, 64) => Encoding::F64,
+      _ => {
+        return Err(CaptureError::UnsupportedFormat(format!(
+          "{} samples with {} bits",
+          format.sample_type, bits
+        )))
+      }
+    };
+    if format.channels == 0 {
+      return Err(CaptureError::UnsupportedFormat(
+        "stream without channels".to_string(),
+      ));
+    }
+    if valid_bits > bits || (format.sample_type == SampleType::Float && valid_bits != bits) {
+      return Err(CaptureError::UnsupportedFormat(format!(
+        "{} valid bits in a {} bit {} sample",
+        valid_bits, bits, format.sample_type
+      )));
+    }
+
+    // 整数样本先左对齐到 32 位，再屏蔽掉无效的低位
+    let valid_mask = match encoding {
+      Encoding::F32 | Encoding::F64 => -1,
+      _ => -1i32 << (32 - valid_bits as u32),
+    };
+
+    Ok(PcmDecoder {
+      encoding,
+      channels: format.channels as usize,
+      sample_bytes: bits as usize / 8,
+      block_align: format.block_align(),
+      valid_mask,
+    })
+  }
+
+  pub fn channels(&self) -> usize {
+    self.channels
+  }
+
+  /// Number of bytes in one interleaved frame.
+  pub fn block_align(&self) -> usize {
+    self.block_align
+  }
+
+  /// Decode a single sample. `bytes` must hold exactly one sample of the stream format.
+  pub fn decode_sample(&self, bytes: &[u8]) -> f32 {
+    let value = match self.encoding {
+      Encoding::U8 => ((bytes[0] as i32) - 128) << 24,
+      Encoding::I16 => (i16::from_le_bytes([bytes[0], bytes[1]]) as i32) << 16,
+      Encoding::I24 => i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]),
+      Encoding::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+      Encoding::F32 => return f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+      Encoding::F64 => {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&bytes[..8]);
+        return f64::from_le_bytes(raw) as f32;
+      }
+    };
+    (value & self.valid_mask) as f32 / 2147483648.0
+  }
+
+  /// De-interleave the complete frames in `bytes` into one buffer per channel.
+  ///
+  /// Decodes at most as many frames as the shortest buffer in `out` can hold, and returns
+  /// the number of frames written. Trailing partial frames are left untouched.
+  pub fn decode(&self, bytes: &[u8], out: &mut [Vec<f32>]) -> usize {
+    let capacity = out.iter().map(|c| c.len()).min().unwrap_or(0);
+    let frames = (bytes.len() / self.block_align).min(capacity);
+
+    for (i, frame) in bytes
+      .chunks_exact(self.block_align)
+      .take(frames)
+      .enumerate()
+    {
+      for (samples, sample) in out.iter_mut().zip(frame.chunks_exact(self.sample_bytes)) {
+        samples[i] = self.decode_sample(sample);
+      }
+    }
+
+    frames
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn int_format(channels: u16, bits: u16, valid_bits: u16) -> StreamFormat {
+    let mut format = StreamFormat::new(48000, channels, bits, SampleType::Int);
+    format.valid_bits = valid_bits;
+    format
+  }
+
+  fn decode_all(format: StreamFormat, bytes: &[u8]) -> Vec<Vec<f32>> {
+    let decoder = PcmDecoder::new(&format).unwrap();
+    let frames = bytes.len() / decoder.block_align();
+    let mut out = vec![vec![0.0; frames]; decoder.channels()];
+    assert_eq!(decoder.decode(bytes, &mut out), frames);
+    out
+  }
+
+  #[test]
+  fn decodes_unsigned_8_bit() {
+    let out = decode_all(int_format(1, 8, 8), &[0, 64, 128, 255]);
+    assert_eq!(out[0], vec![-1.0, -0.5, 0.0, 127.0 / 128.0]);
+  }
+
+  #[test]
+  fn decodes_signed_16_and_24_bit() {
+    let bytes: Vec<u8> = [-32768i16, 16384, 0, 32767]
+      .iter()
+      .flat_map(|s| s.to_le_bytes())
+      .collect();
+    let out = decode_all(int_format(2, 16, 16), &bytes);
+    assert_eq!(out, vec![vec![-1.0, 0.0], vec![0.5, 32767.0 / 32768.0]]);
+
+    let out = decode_all(int_format(1, 24, 24), &[0x00, 0x00, 0x40, 0x00, 0x00, 0xC0]);
+    assert_eq!(out[0], vec![0.5, -0.5]);
+  }
+
+  #[test]
+  fn decodes_24_bit_packed_in_32() {
+    // 左对齐的 24 位样本，最低字节为无效数据
+    let bytes = [0x7F, 0x00, 0x00, 0x40, 0xFF, 0x00, 0x00, 0xC0];
+    let out = decode_all(int_format(1, 32, 24), &bytes);
+    assert_eq!(out[0], vec![0.5, -0.5]);
+
+    // 未声明有效位数时，所有位都参与计算
+    let out = decode_all(int_format(1, 32, 0), &bytes);
+    assert_eq!(out[0][0], 0x4000007F as f32 / 2147483648.0);
+  }
+
+  #[test]
+  fn decodes_float_32_and_64_bit() {
+    let bytes: Vec<u8> = [0.25f32, -0.75]
+      .iter()
+      .flat_map(|s| s.to_le_bytes())
+      .collect();
+    let format = StreamFormat::new(48000, 2, 32, SampleType::Float);
+    assert_eq!(decode_all(format, &bytes), vec![vec![0.25], vec![-0.75]]);
+
+    let bytes: Vec<u8> = [0.125f64, -1.0, 0.5]
+      .iter()
+      .flat_map(|s| s.to_le_bytes())
+      .collect();
+    let format = StreamFormat::new(48000, 1, 64, SampleType::Float);
+    assert_eq!(decode_all(format, &bytes), vec![vec![0.125, -1.0, 0.5]]);
+  }
+
+  #[test]
+  fn stops_at_capacity_and_partial_frames() {
+    let decoder = PcmDecoder::new(&int_format(2, 16, 16)).unwrap();
+    let bytes = [0u8; 4 * 3 + 2];
+
+    let mut out = vec![vec![1.0; 8]; 2];
+    assert_eq!(decoder.decode(&bytes, &mut out), 3);
+    assert_eq!(out[0][..4], [0.0, 0.0, 0.0, 1.0]);
+
+    let mut out = vec![vec![1.0; 2]; 2];
+    assert_eq!(decoder.decode(&bytes, &mut out), 2);
+  }
+
+  #[test]
+  fn rejects_unsupported_formats() {
+    let unsupported = [
+      int_format(2, 12, 12),
+      int_format(2, 16, 20),
+      int_format(0, 16, 16),
+      StreamFormat::new(48000, 2, 16, SampleType::Float),
+    ];
+    for format in unsupported {
+      assert!(
+        matches!(
+          PcmDecoder::new(&format),
+          Err(CaptureError::UnsupportedFormat(_))
+        ),
+        "{:?}",
+        format
+      );
+    }
+  }
+}
@@ -0,0 +1,333 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use crate::source::pacing::Pacer;
+use crate::source::{
+  CaptureError, CaptureRes, CaptureSource, SampleType, StreamFormat, READ_BLOCK_MS,
+};
+use log::{debug, info};
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+// fmt 块最长 40 字节（WAVE_FORMAT_EXTENSIBLE），更大的长度只可能来自损坏的文件
+const MAX_FMT_CHUNK: u64 = 1024;
+
+/// Plays back a PCM or IEEE float WAV file as a [CaptureSource].
+///
+/// Frames are delivered in the native sample format of the file.
+/// With `realtime` set, frames are delivered at the rate of the file's sample rate,
+/// otherwise they are delivered as fast as the monitor consumes them.
+pub struct WavSource {
+  path: PathBuf,
+  realtime: bool,
+  reader: Option<WavReader<BufReader<File>>>,
+  pacer: Pacer,
+}
+
+impl WavSource {
+  pub fn new(path: impl Into<PathBuf>, realtime: bool) -> Self {
+    WavSource {
+      path: path.into(),
+      realtime,
+      reader: None,
+      pacer: Pacer::new(44100),
+    }
+  }
+}
+
+impl CaptureSource for WavSource {
+  fn open(&mut self) -> CaptureRes<()> {
+    let file = File::open(&self.path)?;
+    let reader = WavReader::new(BufReader::new(file))?;
+    debug!("Opened wav file {:?}: {:?}", self.path, reader.format());
+    info!("Wav playback started");
+
+    self.pacer = Pacer::new(reader.format().sample_rate);
+    self.reader = Some(reader);
+    Ok(())
+  }
+
+  fn format(&self) -> StreamFormat {
+    self
+      .reader
+      .as_ref()
+      .map_or(StreamFormat::new(44100, 2, 32, SampleType::Float), |r| {
+        r.format()
+      })
+  }
+
+  fn read(&mut self, buffer: &mut VecDeque<u8>) -> CaptureRes<bool> {
+    let reader = self.reader.as_mut().ok_or(CaptureError::NotOpen)?;
+    let sample_rate = reader.format().sample_rate as u64;
+    let block_frames = (sample_rate * READ_BLOCK_MS / 1000).max(1) as usize;
+
+    if self.realtime {
+      // 按采样率节流，使播放速度与实际时间一致
+      self.pacer.wait();
+    }
+
+    let frames = reader.read_frames(block_frames, buffer)?;
+    self.pacer.advance(frames);
+    Ok(frames > 0)
+  }
+
+  fn stop(&mut self) {
+    if self.reader.take().is_some() {
+      info!("Wav playback stopped");
+    }
+  }
+}
+
+/// Minimal RIFF/WAVE reader returning the raw frames of the data chunk.
+pub(crate) struct WavReader<R> {
+  reader: R,
+  format: StreamFormat,
+  remaining: u64,
+  scratch: Vec<u8>,
+}
+
+impl<R: Read + Seek> WavReader<R> {
+  pub fn new(mut reader: R) -> CaptureRes<Self> {
+    let mut riff = [0u8; 12];
+    reader.read_exact(&mut riff)?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+      return Err(CaptureError::InvalidWav(
+        "missing RIFF/WAVE header".to_string(),
+      ));
+    }
+
+    let mut format = None;
+    loop {
+      let mut header = [0u8; 8];
+      reader
+        .read_exact(&mut header)
+        .map_err(|_| CaptureError::InvalidWav("no data chunk found".to_string()))?;
+      let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+
+      match &header[0..4] {
+        b"fmt " => {
+          if size > MAX_FMT_CHUNK {
+            return Err(CaptureError::InvalidWav(format!(
+              "fmt chunk of {} bytes",
+              size
+            )));
+          }
+          let mut chunk = vec![0u8; size as usize];
+          reader.read_exact(&mut chunk)?;
+          format = Some(parse_fmt_chunk(&chunk)?);
+          if size % 2 == 1 {
+            reader.seek(SeekFrom::Current(1))?;
+          }
+        }
+        b"data" => {
+          let format =
+            format.ok_or_else(|| CaptureError::InvalidWav("data chunk before fmt".to_string()))?;
+          // 一些写入器在流式录制时不会回填长度，因此以文件实际长度为准
+          let start = reader.stream_position()?;
+          let end = reader.seek(SeekFrom::End(0))?;
+          reader.seek(SeekFrom::Start(start))?;
+          let remaining = size.min(end - start);
+          return Ok(WavReader {
+            reader,
+            format,
+            remaining,
+            scratch: Vec::new(),
+          });
+        }
+        _ => {
+          reader.seek(SeekFrom::Current((size + size % 2) as i64))?;
+        }
+      }
+    }
+  }
+
+  /// Format of the samples stored in the file.
+  pub fn format(&self) -> StreamFormat {
+    self.format
+  }
+
+  /// Append up to `max_frames` raw frames to `buffer`.
+  /// Returns the number of frames read, zero at the end of the data chunk.
+  pub fn read_frames(&mut self, max_frames: usize, buffer: &mut VecDeque<u8>) -> CaptureRes<usize> {
+    let block_align = self.format.block_align();
+    let frames = (max_frames as u64).min(self.remaining / block_align as u64) as usize;
+    if frames == 0 {
+      return Ok(0);
+    }
+
+    self.scratch.resize(frames * block_align, 0);
+    self.reader.read_exact(&mut self.scratch)?;
+    self.remaining -= self.scratch.len() as u64;
+    buffer.extend(self.scratch.iter());
+    Ok(frames)
+  }
+}
+
+fn parse_fmt_chunk(chunk: &[u8]) -> CaptureRes<StreamFormat> {
+  if chunk.len() < 16 {
+    return Err(CaptureError::InvalidWav("fmt chunk too short".to_string()));
+  }
+  let read_u16 = |offset: usize| u16::from_le_bytes([chunk[offset], chunk[offset + 1]]);
+  let read_u32 = |offset: usize| {
+    u32::from_le_bytes([
+      chunk[offset],
+      chunk[offset + 1],
+      chunk[offset + 2],
+      chunk[offset + 3],
+    ])
+  };
+
+  let mut format_tag = read_u16(0);
+  let channels = read_u16(2);
+  let sample_rate = read_u32(4);
+  let block_align = read_u16(12);
+  let bits_per_sample = read_u16(14);
+  let mut valid_bits = bits_per_sample;
+  let mut channel_mask = None;
+
+  if format_tag == WAVE_FORMAT_EXTENSIBLE {
+    if chunk.len() < 40 {
+      return Err(CaptureError::InvalidWav(
+        "extensible fmt chunk too short".to_string(),
+      ));
+    }
+    valid_bits = read_u16(18);
+    channel_mask = Some(read_u32(20));
+    // SubFormat GUID 的前两个字节即为实际的格式标签
+    format_tag = read_u16(24);
+  }
+
+  let sample_type = match (format_tag, bits_per_sample) {
+    (WAVE_FORMAT_PCM, 8 | 16 | 24 | 32) => SampleType::Int,
+    (WAVE_FORMAT_IEEE_FLOAT, 32 | 64) => SampleType::Float,
+    _ => {
+      return Err(CaptureError::UnsupportedFormat(format!(
+        "format tag {:#06x} with {} bits per sample",
+        format_tag, bits_per_sample
+      )))
+    }
+  };
+
+  if channels == 0 || sample_rate == 0 {
+    return Err(CaptureError::InvalidWav(
+      "zero channels or sample rate".to_string(),
+    ));
+  }
+  if block_align as usize != channels as usize * bits_per_sample as usize / 8 {
+    return Err(CaptureError::UnsupportedFormat(format!(
+      "block align {} for {} channels of {} bits",
+      block_align, channels, bits_per_sample
+    )));
+  }
+
+  let mut format = StreamFormat::new(sample_rate, channels, bits_per_sample, sample_type);
+  format.valid_bits = valid_bits;
+  if let Some(mask) = channel_mask {
+    format.channel_mask = mask;
+  }
+  Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::source::PcmDecoder;
+  use crate::utils::extract_float_samples;
+  use std::io::Cursor;
+
+  fn wav_bytes(format_tag: u16, channels: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+    let block_align = channels * bits / 8;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&format_tag.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&48000u32.to_le_bytes());
+    bytes.extend_from_slice(&(48000 * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&bits.to_le_bytes());
+    bytes.extend_from_slice(b"LIST");
+    bytes.extend_from_slice(&3u32.to_le_bytes());
+    bytes.extend_from_slice(&[0, 0, 0, 0]);
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+  }
+
+  fn read_all(bytes: Vec<u8>) -> (StreamFormat, Vec<f32>) {
+    let mut reader = WavReader::new(Cursor::new(bytes)).unwrap();
+    let format = reader.format();
+    let mut buffer = VecDeque::new();
+    while reader.read_frames(3, &mut buffer).unwrap() > 0 {}
+
+    let frames = buffer.len() / format.block_align();
+    let decoder = PcmDecoder::new(&format).unwrap();
+    let channels = extract_float_samples(&mut buffer, frames, &decoder);
+    let samples = (0..frames)
+      .flat_map(|i| channels.iter().map(move |c| c[i]))
+      .collect();
+    (format, samples)
+  }
+
+  #[test]
+  fn reads_int16_stereo() {
+    let data: Vec<u8> = [16384i16, -32768, 0, 32767]
+      .iter()
+      .flat_map(|s| s.to_le_bytes())
+      .collect();
+    let (format, samples) = read_all(wav_bytes(WAVE_FORMAT_PCM, 2, 16, &data));
+    assert_eq!(format.channels, 2);
+    assert_eq!(format.sample_rate, 48000);
+    assert_eq!(samples, vec![0.5, -1.0, 0.0, 32767.0 / 32768.0]);
+  }
+
+  #[test]
+  fn reads_uint8_and_int24() {
+    let (_, samples) = read_all(wav_bytes(WAVE_FORMAT_PCM, 1, 8, &[0, 128, 192]));
+    assert_eq!(samples, vec![-1.0, 0.0, 0.5]);
+
+    let (_, samples) = read_all(wav_bytes(
+      WAVE_FORMAT_PCM,
+      1,
+      24,
+      &[0x00, 0x00, 0x40, 0x00, 0x00, 0xC0],
+    ));
+    assert_eq!(samples, vec![0.5, -0.5]);
+  }
+
+  #[test]
+  fn reads_float64() {
+    let data: Vec<u8> = [0.25f64, -0.75]
+      .iter()
+      .flat_map(|s| s.to_le_bytes())
+      .collect();
+    let (format, samples) = read_all(wav_bytes(WAVE_FORMAT_IEEE_FLOAT, 2, 64, &data));
+    assert_eq!(format.sample_type, SampleType::Float);
+    assert_eq!(samples, vec![0.25, -0.75]);
+  }
+
+  #[test]
+  fn rejects_unsupported_formats() {
+    let result = WavReader::new(Cursor::new(wav_bytes(0x0055, 2, 16, &[])));
+    assert!(matches!(result, Err(CaptureError::UnsupportedFormat(_))));
+
+    let result = WavReader::new(Cursor::new(b"RIFX\0\0\0\0WAVE".to_vec()));
+    assert!(matches!(result, Err(CaptureError::InvalidWav(_))));
+  }
+
+  #[test]
+  fn rejects_oversized_fmt_chunks() {
+    let mut bytes = wav_bytes(WAVE_FORMAT_PCM, 2, 16, &[]);
+    bytes[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    let result = WavReader::new(Cursor::new(bytes));
+    assert!(matches!(result, Err(CaptureError::InvalidWav(_))));
+  }
+}
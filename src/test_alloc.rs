@@ -0,0 +1,44 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// 只在 `count_allocations` 的作用域内统计当前线程的分配次数，其余时间直接转发给系统分配器
+struct CountingAllocator;
+
+thread_local! {
+  static COUNTING: Cell<bool> = const { Cell::new(false) };
+  static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record_allocation() {
+  if COUNTING.try_with(Cell::get).unwrap_or(false) {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+  }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    record_allocation();
+    System.alloc(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout)
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    record_allocation();
+    System.realloc(ptr, layout, new_size)
+  }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Run `f` and return how many allocations the current thread made meanwhile.
+pub fn count_allocations(f: impl FnOnce()) -> usize {
+  ALLOCATIONS.with(|count| count.set(0));
+  COUNTING.with(|counting| counting.set(true));
+  f();
+  COUNTING.with(|counting| counting.set(false));
+  ALLOCATIONS.with(Cell::get)
+}
@@ -1,9 +1,471 @@
+use crate::source::{SampleType, StreamFormat};
 use napi_derive::napi;
 
 #[napi(object)]
 pub struct AudioDevice {
   pub id: String,
+  /// Name of the endpoint, e.g. `Speakers (Realtek High Definition Audio)`.
   pub name: String,
-  pub state: u32,
+  /// Name of the audio adapter, e.g. `Realtek High Definition Audio`.
+  pub adapter_name: Option<String>,
+  /// Description of the endpoint, e.g. `Speakers`.
+  pub description: Option<String>,
+  pub direction: DeviceDirection,
+  pub state: DeviceState,
+  /// Default device for the console role, the one captured when no device is set.
   pub is_default: bool,
+  pub default_roles: DefaultRoles,
+  /// Shared mode format of the device, only available while it is active.
+  pub mix_format: Option<StreamInfo>,
+}
+
+/// Whether a device plays or records audio.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum DeviceDirection {
+  /// An output device such as speakers or headphones.
+  Render,
+  /// An input device such as a microphone.
+  Capture,
+}
+
+/// State of an audio endpoint.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum DeviceState {
+  Active,
+  /// Disabled in the sound control panel.
+  Disabled,
+  /// The adapter was removed or disabled in the device manager.
+  NotPresent,
+  /// Nothing is plugged into the jack of the device.
+  Unplugged,
+}
+
+/// Roles for which a device is the default endpoint.
+#[napi(object)]
+pub struct DefaultRoles {
+  /// Games, system sounds and most applications.
+  pub console: bool,
+  /// Music and video playback.
+  pub multimedia: bool,
+  /// Voice calls.
+  pub communications: bool,
+}
+
+/// Which devices `getAllOutputDevices` and `getAllInputDevices` list.
+#[napi(object)]
+pub struct DeviceListOptions {
+  /// Only devices in one of these states. Defaults to active devices.
+  pub states: Option<Vec<DeviceState>>,
+}
+
+/// What a default device is used for.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum DeviceRole {
+  Console,
+  Multimedia,
+  Communications,
+}
+
+/// Which endpoints a monitor captures.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+  /// What is played on an output device, the default.
+  Loopback,
+  /// A microphone, line input or other capture device.
+  Input,
+}
+
+#[napi(object)]
+pub struct DeviceOptions {
+  /// Defaults to the current kind of the monitor, loopback for a new one.
+  pub kind: Option<DeviceKind>,
+}
+
+#[napi(object)]
+pub struct ProcessOptions {
+  /// Capture the process and its child processes (true, the default), or everything except them.
+  pub include_tree: Option<bool>,
+}
+
+#[napi(object)]
+pub struct FileSourceOptions {
+  /// Deliver frames at the file's sample rate instead of as fast as possible. Defaults to true.
+  pub realtime: Option<bool>,
+}
+
+#[napi(string_enum = "camelCase")]
+pub enum SignalKind {
+  Sine,
+  MultiTone,
+  Square,
+  Saw,
+  WhiteNoise,
+  PinkNoise,
+  Sweep,
+  Impulse,
+  Silence,
+}
+
+#[napi(object)]
+pub struct GeneratorOptions {
+  pub signal: SignalKind,
+  /// Frequency of tones in Hz, defaults to 1000. Start frequency of sweeps, defaults to 20.
+  pub frequency: Option<f64>,
+  /// Frequencies of a multi tone signal in Hz.
+  pub frequencies: Option<Vec<f64>>,
+  /// End frequency of sweeps in Hz. Defaults to 20000.
+  pub end_frequency: Option<f64>,
+  /// Length of a sweep, or interval between impulses, in seconds. Defaults to 1.
+  pub period: Option<f64>,
+  /// Peak amplitude between 0 and 1. Defaults to 0.5.
+  pub amplitude: Option<f64>,
+  /// Defaults to 44100.
+  pub sample_rate: Option<u32>,
+  /// Defaults to 2.
+  pub channels: Option<u32>,
+  /// Stop after the given number of seconds instead of running forever.
+  pub duration: Option<f64>,
+  /// Deliver frames at the sample rate instead of as fast as possible. Defaults to true.
+  pub realtime: Option<bool>,
+}
+
+/// How the channels of a multi-channel stream are combined for `getSpectrum`.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum DownmixMode {
+  /// Average of all channels.
+  Mono,
+  /// Average of the first two (front left and right) channels.
+  Mid,
+  /// Half the difference of the first two channels.
+  Side,
+  /// Loudest value of each band over all per-channel spectra.
+  Max,
+}
+
+/// Window applied to each block before the FFT. Decibel levels are compensated for the window's coherent gain.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum WindowFunction {
+  Rectangular,
+  Hann,
+  Hamming,
+  Blackman,
+  BlackmanHarris,
+  FlatTop,
+  /// Kaiser-Bessel window, the parameter is beta (defaults to 8.6).
+  Kaiser,
+  /// Gaussian window, the parameter is sigma relative to half the window length (defaults to 0.4).
+  Gaussian,
+}
+
+/// Frequency scale used to group FFT bins into bands.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum BandScale {
+  /// Equal width bands, the default.
+  Linear,
+  Logarithmic,
+  Mel,
+  Bark,
+  /// Equivalent rectangular bandwidth rate.
+  Erb,
+  /// Full octave bands at the ISO 266 centre frequencies.
+  Octave,
+  /// Third-octave bands at the ISO 266 centre frequencies.
+  ThirdOctave,
+}
+
+#[napi(object)]
+pub struct BandLayoutOptions {
+  pub scale: BandScale,
+  /// Lower edge of the first band in Hz. Defaults to 0 for linear and 20 for the other scales.
+  pub min_frequency: Option<f64>,
+  /// Upper edge of the last band in Hz, capped at the Nyquist frequency.
+  /// Defaults to 20000, or the Nyquist frequency for linear.
+  pub max_frequency: Option<f64>,
+}
+
+/// Unit of the values returned by `getSpectrum`.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum SpectrumScale {
+  /// `log10(1 + mean magnitude)` of each band, without window gain compensation, the original scale.
+  Log,
+  /// Band power in dBFS, where a full scale sine reads 0 dB. Never below the floor.
+  Decibels,
+  /// Decibels mapped linearly from floor..ceiling to 0..1.
+  Normalized,
+}
+
+/// Frequency weighting applied to the magnitude spectrum and the level meter.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum FrequencyWeighting {
+  /// Flat response, the default.
+  Z,
+  /// IEC 61672 A-weighting, approximating the loudness of quiet sounds.
+  A,
+  /// IEC 61672 C-weighting, flatter for loud sounds.
+  C,
+  /// ITU-R BS.468 noise weighting.
+  Itu468,
+}
+
+#[napi(object)]
+pub struct ScaleOptions {
+  pub scale: SpectrumScale,
+  /// Lowest level in dBFS. Defaults to -90.
+  pub floor: Option<f64>,
+  /// Level in dBFS mapped to 1 by the normalized scale. Defaults to 0.
+  pub ceiling: Option<f64>,
+}
+
+#[napi(object)]
+pub struct FrameOptions {
+  /// Number of samples per analysis frame, rounded up to a power of two by the FFT.
+  /// Defaults to the chunk size passed to `start`, 2048 if omitted.
+  pub fft_size: Option<u32>,
+  /// Number of new samples between two frames. Defaults to the FFT size, i.e. no overlap.
+  pub hop_size: Option<u32>,
+}
+
+#[napi(object)]
+pub struct SmoothingOptions {
+  /// Time constant in ms for rising values. Defaults to 0, i.e. no smoothing.
+  pub attack: Option<f64>,
+  /// Time constant in ms for falling values. Defaults to 0, i.e. no smoothing.
+  pub release: Option<f64>,
+  /// How long a peak marker stays in place, in ms. Defaults to 0.
+  pub peak_hold: Option<f64>,
+  /// Acceleration of falling peak markers, in spectrum units per second².
+  /// Defaults to 0, i.e. peaks drop to the current value once the hold time is over.
+  pub peak_gravity: Option<f64>,
+}
+
+#[napi(object)]
+pub struct AutoGainOptions {
+  /// How far back the reference level looks, in ms. Defaults to 3000.
+  pub window: Option<f64>,
+  /// Percentile of the recent values mapped to 1, where 100 is the rolling maximum. Defaults to 100.
+  pub percentile: Option<f64>,
+  /// Track every band separately instead of the loudest band. Defaults to false.
+  pub per_band: Option<bool>,
+  /// Time constant in ms with which the gain recovers after loud passages. Defaults to 1000.
+  pub adaptation: Option<f64>,
+  /// Values at or below this level, in the unit of the spectrum scale, stay at 0.
+  /// Defaults to the floor of the scale.
+  pub noise_gate: Option<f64>,
+}
+
+#[napi(object, object_from_js = false)]
+pub struct SpectrumFrame {
+  /// Smoothed band values, as returned by `getSpectrum`.
+  pub values: Vec<f32>,
+  /// Peak-hold marker of every band.
+  pub peaks: Vec<f32>,
+}
+
+#[napi(object)]
+pub struct SpectrumCallbackOptions {
+  /// Number of bands of every frame. Defaults to 64.
+  pub num_bands: Option<u32>,
+  /// Highest number of callbacks per second, frames in between are skipped. Unlimited by default.
+  pub max_rate: Option<f64>,
+}
+
+#[napi(object, object_from_js = false)]
+pub struct SpectrumEvent {
+  /// Band values, as returned by `getSpectrum`.
+  pub values: Vec<f32>,
+  /// Peak-hold marker of every band.
+  pub peaks: Vec<f32>,
+  /// Time the frame was analysed, in ms since the Unix epoch.
+  pub timestamp: f64,
+  /// Index of the frame since `start`. Gaps mean frames were skipped.
+  pub sequence: i64,
+}
+
+/// What a `frames()` reader does with new frames while its buffer is full.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum FrameOverflow {
+  /// Drop the oldest buffered frame, so that a slow reader never holds up the capture. The default.
+  DropOldest,
+  /// Pause the analysis until the reader has room again, so that it gets every frame, e.g. of a
+  /// file. Other consumers pause as well, and device audio captured meanwhile is lost.
+  Block,
+}
+
+#[napi(object)]
+pub struct FrameStreamOptions {
+  /// Number of bands of every frame. Defaults to 64.
+  pub num_bands: Option<u32>,
+  /// Highest number of frames per second, frames in between are skipped. Unlimited by default.
+  pub max_rate: Option<f64>,
+  /// Frames kept while the reader is busy. Defaults to 4.
+  pub buffer_size: Option<u32>,
+  /// What happens to new frames once `bufferSize` frames are waiting. Defaults to `dropOldest`.
+  pub overflow: Option<FrameOverflow>,
+}
+
+/// Result of `FrameReader.next()`, as expected by the async iterator protocol.
+#[napi(object, object_from_js = false)]
+pub struct FrameResult {
+  pub value: Option<SpectrumEvent>,
+  pub done: bool,
+}
+
+/// Format requested from the capture device instead of its shared mode mix format.
+#[napi(object)]
+pub struct CaptureFormat {
+  /// Defaults to 48000.
+  pub sample_rate: Option<u32>,
+  /// Defaults to 2.
+  pub channels: Option<u32>,
+  /// Defaults to float.
+  pub sample_type: Option<SampleType>,
+  /// Defaults to 32 for float and 16 for int samples.
+  pub bits_per_sample: Option<u32>,
+}
+
+/// Category of an error that stopped the monitor.
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+  /// The device was not found or is no longer available.
+  DeviceNotFound,
+  /// The device was unplugged, disabled or its format changed while capturing.
+  DeviceInvalidated,
+  /// The device is used in exclusive mode by another application.
+  DeviceInUse,
+  /// The Windows audio service is not running.
+  ServiceNotRunning,
+  /// The requested or delivered format is not supported.
+  UnsupportedFormat,
+  /// No data arrived from the device in time.
+  Timeout,
+  /// Access to the device was denied.
+  AccessDenied,
+  /// The file is not a supported wav file.
+  InvalidFile,
+  /// Reading the file failed.
+  Io,
+  /// Any other error.
+  Unknown,
+}
+
+/// Error that stopped the monitor.
+#[napi(object, object_from_js = false)]
+#[derive(Clone)]
+pub struct MonitorError {
+  pub code: ErrorCode,
+  pub message: String,
+  /// HRESULT of the failing Windows call, if any.
+  pub hresult: Option<i32>,
+  /// Time the error occurred, in ms since the Unix epoch.
+  pub timestamp: f64,
+}
+
+/// How the monitor reopens a device that was unplugged, disabled or changed its format.
+#[napi(object)]
+pub struct ReconnectOptions {
+  /// Defaults to true.
+  pub enabled: Option<bool>,
+  /// Delay in ms before the first attempt, doubled for every further attempt. Defaults to 100.
+  pub initial_delay: Option<f64>,
+  /// Longest delay in ms between two attempts. Defaults to 5000.
+  pub max_delay: Option<f64>,
+  /// Attempts before giving up and reporting the error. Unlimited by default.
+  pub max_attempts: Option<u32>,
+}
+
+/// Passed to `onReconnect` once the stream has been reopened.
+#[napi(object, object_from_js = false)]
+pub struct ReconnectEvent {
+  /// Error that interrupted the stream.
+  pub code: ErrorCode,
+  pub message: String,
+  /// Number of attempts it took to reopen the stream.
+  pub attempts: u32,
+  /// Time the stream was reopened, in ms since the Unix epoch.
+  pub timestamp: f64,
+}
+
+/// Passed to `onDeviceSwitch` when a monitor following the default device switched to a new one.
+#[napi(object, object_from_js = false)]
+pub struct DeviceSwitchEvent {
+  /// Device captured until now, if it was known.
+  pub previous_id: Option<String>,
+  /// New default device that is captured from now on.
+  pub device_id: String,
+  /// Time of the switch, in ms since the Unix epoch.
+  pub timestamp: f64,
+}
+
+/// Passed to `DeviceWatcher` callbacks when a device was added, removed or changed its state.
+#[napi(object, object_from_js = false)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceEvent {
+  pub device_id: String,
+  /// State before the change, unknown for devices added while watching.
+  pub previous_state: Option<DeviceState>,
+  /// State after the change, not set for added and removed devices.
+  pub state: Option<DeviceState>,
+  /// Time of the notification, in ms since the Unix epoch.
+  pub timestamp: f64,
+}
+
+/// Passed to `onDefaultChanged` when the default device of a role changed.
+#[napi(object, object_from_js = false)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefaultDeviceEvent {
+  pub direction: DeviceDirection,
+  pub role: DeviceRole,
+  /// Previous default device, if there was one.
+  pub previous_id: Option<String>,
+  /// New default device, not set when no device of that direction is left.
+  pub device_id: Option<String>,
+  /// Time of the notification, in ms since the Unix epoch.
+  pub timestamp: f64,
+}
+
+/// Format of the stream currently being analysed.
+#[napi(object)]
+pub struct StreamInfo {
+  pub sample_rate: u32,
+  pub channels: u32,
+  pub bits_per_sample: u32,
+  pub valid_bits_per_sample: u32,
+  pub sample_type: SampleType,
+  pub channel_mask: u32,
+  /// Speaker position of every channel, e.g. `FrontLeft`, `LowFrequency`.
+  pub channel_names: Vec<String>,
+}
+
+impl From<DeviceKind> for DeviceDirection {
+  fn from(kind: DeviceKind) -> Self {
+    match kind {
+      DeviceKind::Loopback => DeviceDirection::Render,
+      DeviceKind::Input => DeviceDirection::Capture,
+    }
+  }
+}
+
+impl From<&StreamFormat> for StreamInfo {
+  fn from(format: &StreamFormat) -> Self {
+    StreamInfo {
+      sample_rate: format.sample_rate,
+      channels: format.channels as u32,
+      bits_per_sample: format.bits_per_sample as u32,
+      valid_bits_per_sample: format.valid_bits as u32,
+      sample_type: format.sample_type,
+      channel_mask: format.channel_mask,
+      channel_names: format.channel_names(),
+    }
+  }
 }
@@ -1,95 +1,366 @@
-use crate::types::AudioDevice;
-use crate::wasapi::{get_default_device, initialize_mta, Device, DeviceCollection, Direction};
-use napi::Result;
+#[cfg(windows)]
+use crate::devices::DeviceList;
+use crate::source::PcmDecoder;
+#[cfg(windows)]
+use crate::source::{CaptureRes, StreamFormat};
+#[cfg(windows)]
+use crate::types::{
+  AudioDevice, DefaultRoles, DeviceDirection, DeviceKind, DeviceListOptions, DeviceRole,
+  DeviceState, StreamInfo,
+};
+#[cfg(windows)]
+use crate::wasapi::{
+  get_default_device, get_default_device_for_role, initialize_mta, Device, DeviceCollection,
+  DeviceState as WasapiDeviceState, Direction, Role, WasapiError,
+};
+#[cfg(windows)]
+use log::warn;
+use napi::{Error, Result, Status};
+#[cfg(windows)]
 use napi_derive::napi;
 use std::collections::VecDeque;
+use std::sync::Mutex;
 
-pub fn get_output_device_by_id(id: String) -> Option<Device> {
-  for device in &DeviceCollection::new(&Direction::Render).unwrap() {
-    let dev = device.unwrap();
-    if dev.get_id().unwrap() == id {
-      return Some(dev);
+#[cfg(windows)]
+impl From<DeviceKind> for Direction {
+  fn from(kind: DeviceKind) -> Self {
+    match kind {
+      DeviceKind::Loopback => Direction::Render,
+      DeviceKind::Input => Direction::Capture,
     }
   }
-  None
 }
 
-#[napi]
-pub fn get_all_output_devices() -> Result<Vec<AudioDevice>> {
-  let mut output_devices = Vec::new();
-  let default_output_device = get_default_output_device()?;
-
-  for device in &DeviceCollection::new(&Direction::Render).unwrap() {
-    let dev = device.unwrap();
-    let id = dev.get_id().unwrap();
-    let name = dev.get_friendlyname().unwrap();
-    let state = dev.get_state().unwrap() as u32;
-    let is_default = default_output_device.as_ref().map_or(false, |d| d.id == id);
-
-    output_devices.push(AudioDevice {
-      id,
-      name,
-      state,
-      is_default,
-    });
+#[cfg(windows)]
+impl From<Direction> for DeviceDirection {
+  fn from(direction: Direction) -> Self {
+    match direction {
+      Direction::Render => DeviceDirection::Render,
+      Direction::Capture => DeviceDirection::Capture,
+    }
+  }
+}
+
+#[cfg(windows)]
+impl From<Role> for DeviceRole {
+  fn from(role: Role) -> Self {
+    match role {
+      Role::Console => DeviceRole::Console,
+      Role::Multimedia => DeviceRole::Multimedia,
+      Role::Communications => DeviceRole::Communications,
+    }
   }
+}
 
-  Ok(output_devices)
+#[cfg(windows)]
+impl From<WasapiDeviceState> for DeviceState {
+  fn from(state: WasapiDeviceState) -> Self {
+    match state {
+      WasapiDeviceState::Active => DeviceState::Active,
+      WasapiDeviceState::Disabled => DeviceState::Disabled,
+      WasapiDeviceState::NotPresent => DeviceState::NotPresent,
+      WasapiDeviceState::Unplugged => DeviceState::Unplugged,
+    }
+  }
+}
+
+#[cfg(windows)]
+impl From<DeviceState> for WasapiDeviceState {
+  fn from(state: DeviceState) -> Self {
+    match state {
+      DeviceState::Active => WasapiDeviceState::Active,
+      DeviceState::Disabled => WasapiDeviceState::Disabled,
+      DeviceState::NotPresent => WasapiDeviceState::NotPresent,
+      DeviceState::Unplugged => WasapiDeviceState::Unplugged,
+    }
+  }
+}
+
+/// Active endpoints as reported by Windows.
+#[cfg(windows)]
+pub struct SystemDevices;
+
+#[cfg(windows)]
+impl DeviceList for SystemDevices {
+  fn devices(&self, kind: DeviceKind) -> Vec<String> {
+    let Ok(collection) = DeviceCollection::new(&Direction::from(kind)) else {
+      return Vec::new();
+    };
+    collection
+      .into_iter()
+      .filter_map(|device| device.ok()?.get_id().ok())
+      .collect()
+  }
+
+  fn default_device(&self, kind: DeviceKind) -> Option<String> {
+    get_default_device(&Direction::from(kind))
+      .ok()?
+      .get_id()
+      .ok()
+  }
 }
 
+/// Active endpoint with the given id, or `None` if there is none.
+#[cfg(windows)]
+pub fn get_device_by_id(direction: &Direction, id: &str) -> CaptureRes<Option<Device>> {
+  // 无法读取的设备（例如正在移除的设备）被跳过，与列出设备时一致
+  for device in &DeviceCollection::new(direction)? {
+    let device = match device {
+      Ok(device) => device,
+      Err(err) => {
+        warn!("Skipping device that could not be read: {}", err);
+        continue;
+      }
+    };
+    match device.get_id() {
+      Ok(device_id) if device_id == id => return Ok(Some(device)),
+      Ok(_) => {}
+      Err(err) => warn!("Skipping device that could not be read: {}", err),
+    }
+  }
+  Ok(None)
+}
+
+#[cfg(windows)]
+#[napi]
+pub fn get_all_output_devices(options: Option<DeviceListOptions>) -> Result<Vec<AudioDevice>> {
+  get_all_devices(&Direction::Render, options)
+}
+
+#[cfg(windows)]
 #[napi]
 pub fn get_default_output_device() -> Result<Option<AudioDevice>> {
-  initialize_mta().unwrap();
+  get_default(&Direction::Render)
+}
+
+/// Microphones, line inputs and other capture endpoints.
+#[cfg(windows)]
+#[napi]
+pub fn get_all_input_devices(options: Option<DeviceListOptions>) -> Result<Vec<AudioDevice>> {
+  get_all_devices(&Direction::Capture, options)
+}
+
+#[cfg(windows)]
+#[napi]
+pub fn get_default_input_device() -> Result<Option<AudioDevice>> {
+  get_default(&Direction::Capture)
+}
+
+#[cfg(windows)]
+fn get_all_devices(
+  direction: &Direction,
+  options: Option<DeviceListOptions>,
+) -> Result<Vec<AudioDevice>> {
+  let states: Vec<WasapiDeviceState> = match options.and_then(|options| options.states) {
+    Some(states) => states.into_iter().map(Into::into).collect(),
+    None => vec![WasapiDeviceState::Active],
+  };
+  // 空的状态掩码不是有效参数
+  if states.is_empty() {
+    return Ok(Vec::new());
+  }
 
-  let device = match get_default_device(&Direction::Render) {
+  let defaults = DefaultDevices::new(direction);
+  let collection = DeviceCollection::with_states(direction, &states).map_err(napi_error)?;
+
+  // 无法读取的设备（例如正在移除的设备）被跳过，不影响列出其他设备
+  Ok(
+    collection
+      .into_iter()
+      .filter_map(|device| {
+        match device
+          .map_err(napi_error)
+          .and_then(|device| audio_device(&device, &defaults))
+        {
+          Ok(device) => Some(device),
+          Err(err) => {
+            warn!("Skipping device that could not be read: {}", err);
+            None
+          }
+        }
+      })
+      .collect(),
+  )
+}
+
+#[cfg(windows)]
+fn get_default(direction: &Direction) -> Result<Option<AudioDevice>> {
+  let defaults = DefaultDevices::new(direction);
+
+  let device = match get_default_device(direction) {
     Ok(device) => device,
     Err(_) => return Ok(None),
   };
 
-  let id = device.get_id().unwrap();
-  let name = device.get_friendlyname().unwrap();
-  let state = device.get_state().unwrap() as u32;
+  audio_device(&device, &defaults).map(Some)
+}
+
+#[cfg(windows)]
+fn audio_device(device: &Device, defaults: &DefaultDevices) -> Result<AudioDevice> {
+  let id = device.get_id().map_err(napi_error)?;
+  let name = device.get_friendlyname().map_err(napi_error)?;
+  let state = device.get_state().map_err(napi_error)?;
+  // 只有活动设备才能激活音频客户端获取混音格式
+  let mix_format = match state {
+    WasapiDeviceState::Active => device
+      .get_iaudioclient()
+      .and_then(|client| client.get_mixformat())
+      .ok()
+      .and_then(|format| StreamFormat::try_from(&format).ok())
+      .map(|format| StreamInfo::from(&format)),
+    _ => None,
+  };
+  let default_roles = defaults.roles(&id);
 
-  Ok(Some(AudioDevice {
+  Ok(AudioDevice {
+    adapter_name: device.get_interface_friendlyname().ok(),
+    description: device.get_description().ok(),
+    direction: device.get_direction().into(),
+    state: state.into(),
+    is_default: default_roles.console,
+    default_roles,
+    mix_format,
     id,
     name,
-    state,
-    is_default: true,
-  }))
+  })
+}
+
+// 各角色默认设备的ID
+#[cfg(windows)]
+struct DefaultDevices {
+  console: Option<String>,
+  multimedia: Option<String>,
+  communications: Option<String>,
+}
+
+#[cfg(windows)]
+impl DefaultDevices {
+  fn new(direction: &Direction) -> Self {
+    initialize_mta().unwrap();
+
+    let id = |role| {
+      get_default_device_for_role(direction, &role)
+        .ok()?
+        .get_id()
+        .ok()
+    };
+    DefaultDevices {
+      console: id(Role::Console),
+      multimedia: id(Role::Multimedia),
+      communications: id(Role::Communications),
+    }
+  }
+
+  fn roles(&self, id: &str) -> DefaultRoles {
+    let is = |default: &Option<String>| default.as_deref() == Some(id);
+    DefaultRoles {
+      console: is(&self.console),
+      multimedia: is(&self.multimedia),
+      communications: is(&self.communications),
+    }
+  }
 }
 
+#[cfg(windows)]
+fn napi_error(error: WasapiError) -> Error {
+  Error::new(Status::GenericFailure, error.to_string())
+}
+
+/// Read up to `chunk_size` interleaved frames from the queue,
+/// converting them to f32 and splitting them per channel.
 pub fn extract_float_samples(
   sample_queue: &mut VecDeque<u8>,
   chunk_size: usize,
-  blockalign: usize,
-) -> Vec<f32> {
-  let mut float_samples = vec![0.0f32; chunk_size];
-
-  for i in 0..chunk_size {
-    let offset = i * blockalign;
-    if offset + 4 <= sample_queue.len() {
-      // 读取一个浮点样本（4字节）
-      let bytes = [
-        sample_queue[offset],
-        sample_queue[offset + 1],
-        sample_queue[offset + 2],
-        sample_queue[offset + 3],
-      ];
-      float_samples[i] = f32::from_le_bytes(bytes);
-
-      // 移除已处理的字节
-      for _ in 0..4 {
-        sample_queue.pop_front();
-      }
+  decoder: &PcmDecoder,
+) -> Vec<Vec<f32>> {
+  let mut float_samples = vec![vec![0.0f32; chunk_size]; decoder.channels()];
+  let frames = decoder.decode(sample_queue.make_contiguous(), &mut float_samples);
+
+  // 移除已处理的数据
+  sample_queue.drain(..frames * decoder.block_align());
+
+  float_samples
+}
+
+/// Replace the value behind `mutex`, e.g. a registered callback.
+pub(crate) fn set_locked<T>(mutex: &Mutex<T>, value: T) -> Result<()> {
+  *mutex
+    .lock()
+    .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))? = value;
+  Ok(())
+}
 
-      // 如果是立体声，跳过第二个通道数据
-      if offset + 8 <= sample_queue.len() {
-        for _ in 0..4 {
-          sample_queue.pop_front();
+/// Check an optional number from JavaScript: it must be finite and satisfy `valid`, which
+/// `requirement` describes, e.g. `" above 0"`.
+pub(crate) fn check_value(
+  name: &str,
+  value: Option<f64>,
+  valid: impl Fn(f64) -> bool,
+  requirement: &str,
+) -> Result<()> {
+  match value {
+    Some(value) if !(value.is_finite() && valid(value)) => Err(Error::new(
+      Status::InvalidArg,
+      format!("{} {} must be a finite number{}", name, value, requirement),
+    )),
+    _ => Ok(()),
+  }
+}
+
+/// Channel count from JavaScript, between 1 and `u16::MAX`.
+pub(crate) fn channel_count(channels: u32) -> Result<u16> {
+  u16::try_from(channels)
+    .ok()
+    .filter(|&channels| channels > 0)
+    .ok_or_else(|| {
+      Error::new(
+        Status::InvalidArg,
+        format!(
+          "Channel count {} must be between 1 and {}",
+          channels,
+          u16::MAX
+        ),
+      )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn checks_values() {
+    let positive = |value| check_value("delay", value, |v| v > 0.0, " above 0");
+    for (value, message) in [
+      (Some(-1.0), Some("delay -1 must be a finite number above 0")),
+      (Some(0.0), Some("delay 0 must be a finite number above 0")),
+      (
+        Some(f64::NAN),
+        Some("delay NaN must be a finite number above 0"),
+      ),
+      (
+        Some(f64::INFINITY),
+        Some("delay inf must be a finite number above 0"),
+      ),
+      (Some(0.5), None),
+      (None, None),
+    ] {
+      match (positive(value), message) {
+        (Err(err), Some(message)) => {
+          assert_eq!(err.status, Status::InvalidArg);
+          assert_eq!(err.reason, message);
         }
+        (Ok(()), None) => {}
+        (result, _) => panic!("{:?}: {:?}", value, result),
       }
     }
-  }
 
-  float_samples
+    // 只要求有限值
+    let finite = |value| check_value("gate", value, |_| true, "");
+    assert_eq!(
+      finite(Some(f64::NEG_INFINITY)).unwrap_err().reason,
+      "gate -inf must be a finite number"
+    );
+    assert!(finite(Some(-60.0)).is_ok());
+  }
 }
@@ -449,10 +449,7 @@ impl AudioClient {
   /// Use this to find out how much free space is available in the buffer.
   pub fn get_available_space_in_frames(&self) -> WasapiRes<u32> {
     let frames = match self.sharemode {
-      Some(ShareMode::Exclusive) => {
-        let buffer_frame_count = unsafe { self.client.GetBufferSize()? };
-        buffer_frame_count
-      }
+      Some(ShareMode::Exclusive) => unsafe { self.client.GetBufferSize()? },
       Some(ShareMode::Shared) => {
         let padding_count = unsafe { self.client.GetCurrentPadding()? };
         let buffer_frame_count = unsafe { self.client.GetBufferSize()? };
@@ -544,15 +541,34 @@ impl AudioSessionControl {
     Ok(sessionstate)
   }
 
-  /// Register to receive notifications
-  pub fn register_session_notification(&self, callbacks: Weak<EventCallbacks>) -> WasapiRes<()> {
+  /// Register to receive notifications. The registration stays active until it is passed to
+  /// [AudioSessionControl::unregister_session_notification].
+  pub fn register_session_notification(
+    &self,
+    callbacks: Weak<EventCallbacks>,
+  ) -> WasapiRes<EventRegistration> {
     let events: IAudioSessionEvents = AudioSessionEvents::new(callbacks).into();
 
     match unsafe { self.control.RegisterAudioSessionNotification(&events) } {
-      Ok(()) => Ok(()),
+      Ok(()) => Ok(EventRegistration { events }),
       Err(err) => Err(WasapiError::RegisterNotifications(err)),
     }
   }
+
+  /// Stop receiving the notifications of `registration`.
+  pub fn unregister_session_notification(&self, registration: EventRegistration) -> WasapiRes<()> {
+    unsafe {
+      self
+        .control
+        .UnregisterAudioSessionNotification(&registration.events)?
+    };
+    Ok(())
+  }
+}
+
+/// Session notifications registered with [AudioSessionControl::register_session_notification].
+pub struct EventRegistration {
+  events: IAudioSessionEvents,
 }
 
 /// Struct wrapping an [IAudioClock](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nn-audioclient-iaudioclock).
@@ -6,8 +6,8 @@ use windows::Win32::Devices::FunctionDiscovery::{
 use windows::Win32::Foundation::PROPERTYKEY;
 use windows::Win32::Media::Audio::{
   EDataFlow, IAudioClient, IMMDevice, IMMDeviceCollection, IMMDeviceEnumerator, IMMEndpoint,
-  MMDeviceEnumerator, DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED, DEVICE_STATE_NOTPRESENT,
-  DEVICE_STATE_UNPLUGGED,
+  MMDeviceEnumerator, DEVICE_STATE, DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED,
+  DEVICE_STATE_NOTPRESENT, DEVICE_STATE_UNPLUGGED,
 };
 use windows::Win32::System::Com::StructuredStorage::PropVariantToStringAlloc;
 use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_ALL, STGM_READ};
@@ -26,10 +26,18 @@ pub struct DeviceCollection {
 impl DeviceCollection {
   /// Get an [IMMDeviceCollection] of all active playback or capture devices
   pub fn new(direction: &Direction) -> WasapiRes<DeviceCollection> {
+    Self::with_states(direction, &[DeviceState::Active])
+  }
+
+  /// Get an [IMMDeviceCollection] of the playback or capture devices in any of the given states
+  pub fn with_states(direction: &Direction, states: &[DeviceState]) -> WasapiRes<DeviceCollection> {
     let dir: EDataFlow = direction.into();
+    let mask = states
+      .iter()
+      .fold(0, |mask, state| mask | device_state_flag(state).0);
     let enumerator: IMMDeviceEnumerator =
       unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
-    let devs = unsafe { enumerator.EnumAudioEndpoints(dir, DEVICE_STATE_ACTIVE)? };
+    let devs = unsafe { enumerator.EnumAudioEndpoints(dir, DEVICE_STATE(mask))? };
     Ok(DeviceCollection {
       collection: devs,
       direction: *direction,
@@ -71,6 +79,30 @@ impl DeviceCollection {
   }
 }
 
+/// The DEVICE_STATE_XXX constant of a [DeviceState]
+fn device_state_flag(state: &DeviceState) -> DEVICE_STATE {
+  match state {
+    DeviceState::Active => DEVICE_STATE_ACTIVE,
+    DeviceState::Disabled => DEVICE_STATE_DISABLED,
+    DeviceState::NotPresent => DEVICE_STATE_NOTPRESENT,
+    DeviceState::Unplugged => DEVICE_STATE_UNPLUGGED,
+  }
+}
+
+impl TryFrom<DEVICE_STATE> for DeviceState {
+  type Error = WasapiError;
+
+  fn try_from(state: DEVICE_STATE) -> Result<Self, Self::Error> {
+    match state {
+      _ if state == DEVICE_STATE_ACTIVE => Ok(DeviceState::Active),
+      _ if state == DEVICE_STATE_DISABLED => Ok(DeviceState::Disabled),
+      _ if state == DEVICE_STATE_NOTPRESENT => Ok(DeviceState::NotPresent),
+      _ if state == DEVICE_STATE_UNPLUGGED => Ok(DeviceState::Unplugged),
+      x => Err(WasapiError::IllegalDeviceState(x.0)),
+    }
+  }
+}
+
 /// Iterator for [DeviceCollection]
 pub struct DeviceCollectionIter<'a> {
   collection: &'a DeviceCollection,
@@ -147,14 +179,7 @@ impl Device {
   pub fn get_state(&self) -> WasapiRes<DeviceState> {
     let state = unsafe { self.device.GetState()? };
     trace!("state: {:?}", state);
-    let state_enum = match state {
-      _ if state == DEVICE_STATE_ACTIVE => DeviceState::Active,
-      _ if state == DEVICE_STATE_DISABLED => DeviceState::Disabled,
-      _ if state == DEVICE_STATE_NOTPRESENT => DeviceState::NotPresent,
-      _ if state == DEVICE_STATE_UNPLUGGED => DeviceState::Unplugged,
-      x => return Err(WasapiError::IllegalDeviceState(x.0)),
-    };
-    Ok(state_enum)
+    DeviceState::try_from(state)
   }
 
   /// Read the friendly name of the endpoint device (for example, "Speakers (XYZ Audio Adapter)")
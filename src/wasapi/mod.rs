@@ -2,6 +2,7 @@ mod audio_client;
 mod device;
 mod errors;
 mod events;
+mod notifications;
 mod types;
 mod utils;
 mod wave_format;
@@ -10,12 +11,16 @@ mod wave_format;
 //   AudioCaptureClient, AudioClient, AudioClock, AudioRenderClient, AudioSessionControl, BufferFlags,
 //   Handle,
 // };
+pub use audio_client::{
+  AudioCaptureClient, AudioClient, AudioSessionControl, EventRegistration, Handle,
+};
 pub use device::{Device, DeviceCollection};
 pub use errors::WasapiError;
-// pub use events::EventCallbacks;
+pub use events::{DisconnectReason, EventCallbacks};
+pub use notifications::{EndpointNotification, EndpointNotifications};
 // pub use types::{DeviceState, Direction, Role, SampleType, SessionState, ShareMode};
-pub use types::{Direction, SampleType, SessionState, ShareMode};
-pub use utils::{get_default_device, initialize_mta};
+pub use types::{DeviceState, Direction, Role, SampleType, SessionState, ShareMode};
+pub use utils::{get_default_device, get_default_device_for_role, initialize_mta};
 pub use wave_format::WaveFormat;
 // pub use utils::{
 //   calculate_period_100ns, deinitialize, get_default_device, get_default_device_for_role,
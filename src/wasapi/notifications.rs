@@ -0,0 +1,125 @@
+use log::trace;
+use widestring::U16CString;
+use windows::{
+  core::{implement, Result, PCWSTR},
+  Win32::Foundation::PROPERTYKEY,
+  Win32::Media::Audio::{
+    EDataFlow, ERole, IMMDeviceEnumerator, IMMNotificationClient, IMMNotificationClient_Impl,
+    MMDeviceEnumerator, DEVICE_STATE,
+  },
+  Win32::System::Com::{CoCreateInstance, CLSCTX_ALL},
+};
+
+use crate::wasapi::types::{DeviceState, Direction, Role};
+use crate::wasapi::WasapiRes;
+
+/// A change of the audio endpoints, an enum representing the calls of an
+/// [IMMNotificationClient](https://learn.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immnotificationclient)
+#[derive(Debug, Clone, PartialEq)]
+pub enum EndpointNotification {
+  /// A new endpoint device was installed.
+  Added(String),
+  /// An endpoint device was uninstalled.
+  Removed(String),
+  /// An endpoint device was plugged in, unplugged, enabled or disabled.
+  StateChanged(String, DeviceState),
+  /// The default device for a role changed, `None` when no device of that direction is left.
+  DefaultChanged(Direction, Role, Option<String>),
+}
+
+type NotificationCallback = Box<dyn Fn(EndpointNotification) + Send + Sync>;
+
+/// Registration of an [IMMNotificationClient], unregistered when dropped.
+pub struct EndpointNotifications {
+  enumerator: IMMDeviceEnumerator,
+  client: IMMNotificationClient,
+}
+
+impl EndpointNotifications {
+  /// Call `callback` for every endpoint notification. The callback is called from a system
+  /// thread and must not block.
+  pub fn register(
+    callback: impl Fn(EndpointNotification) + Send + Sync + 'static,
+  ) -> WasapiRes<Self> {
+    let enumerator: IMMDeviceEnumerator =
+      unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
+    let client: IMMNotificationClient = NotificationClient {
+      callback: Box::new(callback),
+    }
+    .into();
+    unsafe { enumerator.RegisterEndpointNotificationCallback(&client)? };
+    Ok(EndpointNotifications { enumerator, client })
+  }
+}
+
+impl Drop for EndpointNotifications {
+  fn drop(&mut self) {
+    let _ = unsafe {
+      self
+        .enumerator
+        .UnregisterEndpointNotificationCallback(&self.client)
+    };
+  }
+}
+
+/// Wrapper for [IMMNotificationClient](https://learn.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immnotificationclient).
+#[implement(IMMNotificationClient)]
+struct NotificationClient {
+  callback: NotificationCallback,
+}
+
+// 将设备ID字符串转换为 String，空指针表示没有设备
+fn device_id(id: &PCWSTR) -> Option<String> {
+  if id.is_null() {
+    return None;
+  }
+  let wide_id = unsafe { U16CString::from_ptr_str(id.0) };
+  Some(wide_id.to_string_lossy())
+}
+
+impl IMMNotificationClient_Impl for NotificationClient_Impl {
+  fn OnDeviceStateChanged(&self, pwstrdeviceid: &PCWSTR, dwnewstate: DEVICE_STATE) -> Result<()> {
+    trace!("Device state changed: {:?}", dwnewstate);
+    if let (Some(id), Ok(state)) = (device_id(pwstrdeviceid), DeviceState::try_from(dwnewstate)) {
+      (self.callback)(EndpointNotification::StateChanged(id, state));
+    }
+    Ok(())
+  }
+
+  fn OnDeviceAdded(&self, pwstrdeviceid: &PCWSTR) -> Result<()> {
+    trace!("Device added");
+    if let Some(id) = device_id(pwstrdeviceid) {
+      (self.callback)(EndpointNotification::Added(id));
+    }
+    Ok(())
+  }
+
+  fn OnDeviceRemoved(&self, pwstrdeviceid: &PCWSTR) -> Result<()> {
+    trace!("Device removed");
+    if let Some(id) = device_id(pwstrdeviceid) {
+      (self.callback)(EndpointNotification::Removed(id));
+    }
+    Ok(())
+  }
+
+  fn OnDefaultDeviceChanged(
+    &self,
+    flow: EDataFlow,
+    role: ERole,
+    pwstrdefaultdeviceid: &PCWSTR,
+  ) -> Result<()> {
+    trace!("Default device changed");
+    if let (Ok(direction), Ok(role)) = (Direction::try_from(flow), Role::try_from(role)) {
+      (self.callback)(EndpointNotification::DefaultChanged(
+        direction,
+        role,
+        device_id(pwstrdefaultdeviceid),
+      ));
+    }
+    Ok(())
+  }
+
+  fn OnPropertyValueChanged(&self, _pwstrdeviceid: &PCWSTR, _key: &PROPERTYKEY) -> Result<()> {
+    Ok(())
+  }
+}
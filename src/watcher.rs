@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+#[cfg(test)]
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::monitor::timestamp;
+use crate::types::{DefaultDeviceEvent, DeviceDirection, DeviceEvent, DeviceRole, DeviceState};
+use crate::utils::set_locked;
+#[cfg(windows)]
+use crate::wasapi::{
+  get_default_device_for_role, initialize_mta, DeviceCollection, DeviceState as WasapiDeviceState,
+  Direction, EndpointNotification, EndpointNotifications, Role,
+};
+use log::debug;
+use napi::bindgen_prelude::ToNapiValue;
+use napi::threadsafe_function::{
+  ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Env, Error, JsFunction, Result, Status};
+use napi_derive::napi;
+
+// 等待通知的最长时间，决定停止时的延迟
+const WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+// JavaScript 注册的事件回调
+type EventCallback<T> = Box<dyn Fn(T) + Send>;
+
+// `onDeviceAdded`、`onDeviceRemoved` 和 `onDeviceStateChanged` 注册的回调
+type DeviceCallback = EventCallback<DeviceEvent>;
+
+// `onDefaultChanged` 注册的回调
+type DefaultCallback = EventCallback<DefaultDeviceEvent>;
+
+/// A change of the audio endpoints as reported by the system.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceNotification {
+  Added(String),
+  Removed(String),
+  StateChanged(String, DeviceState),
+  /// New default device of a role, `None` when no device of that direction is left.
+  DefaultChanged(DeviceDirection, DeviceRole, Option<String>),
+}
+
+/// Delivers endpoint notifications, e.g. from an `IMMNotificationClient` to a [DeviceWatcher],
+/// or from a watcher to a [DeviceFollower](crate::follow::DeviceFollower).
+pub trait DeviceNotifier: Send {
+  /// Next notification, or `None` if there was none within `timeout`.
+  fn next(&mut self, timeout: Duration) -> Option<DeviceNotification>;
+}
+
+impl DeviceNotifier for Receiver<DeviceNotification> {
+  fn next(&mut self, timeout: Duration) -> Option<DeviceNotification> {
+    self.recv_timeout(timeout).ok()
+  }
+}
+
+// 按顺序返回预设的通知，之后等待超时
+#[cfg(test)]
+pub(crate) struct ScriptedNotifier(pub VecDeque<DeviceNotification>);
+
+#[cfg(test)]
+impl DeviceNotifier for ScriptedNotifier {
+  fn next(&mut self, timeout: Duration) -> Option<DeviceNotification> {
+    let notification = self.0.pop_front();
+    if notification.is_none() {
+      thread::sleep(timeout);
+    }
+    notification
+  }
+}
+
+/// A notification that changed the known devices, passed on to the JavaScript callbacks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceChange {
+  Added(DeviceEvent),
+  Removed(DeviceEvent),
+  StateChanged(DeviceEvent),
+  DefaultChanged(DefaultDeviceEvent),
+}
+
+/// Known devices and default devices, used to drop notifications that change nothing. Windows
+/// repeats some of them, e.g. a state that is set again or a default for every role.
+#[derive(Default)]
+pub struct DeviceTracker {
+  // 设备ID到状态，已添加但状态未知的设备为 None
+  states: HashMap<String, Option<DeviceState>>,
+  defaults: HashMap<(DeviceDirection, DeviceRole), Option<String>>,
+}
+
+impl DeviceTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record a device that exists when watching starts.
+  pub fn insert_device(&mut self, device_id: String, state: DeviceState) {
+    self.states.insert(device_id, Some(state));
+  }
+
+  /// Record the default device of a role when watching starts.
+  pub fn insert_default(
+    &mut self,
+    direction: DeviceDirection,
+    role: DeviceRole,
+    device_id: Option<String>,
+  ) {
+    self.defaults.insert((direction, role), device_id);
+  }
+
+  /// Apply `notification`, returning the change it made to the known devices.
+  pub fn update(&mut self, notification: DeviceNotification) -> Option<DeviceChange> {
+    match notification {
+      DeviceNotification::Added(device_id) => {
+        if self.states.contains_key(&device_id) {
+          return None;
+        }
+        self.states.insert(device_id.clone(), None);
+        Some(DeviceChange::Added(DeviceEvent {
+          device_id,
+          previous_state: None,
+          state: None,
+          timestamp: timestamp(),
+        }))
+      }
+      DeviceNotification::Removed(device_id) => {
+        let previous_state = self.states.remove(&device_id)?;
+        Some(DeviceChange::Removed(DeviceEvent {
+          device_id,
+          previous_state,
+          state: None,
+          timestamp: timestamp(),
+        }))
+      }
+      DeviceNotification::StateChanged(device_id, state) => {
+        let previous_state = self.states.insert(device_id.clone(), Some(state)).flatten();
+        if previous_state == Some(state) {
+          return None;
+        }
+        Some(DeviceChange::StateChanged(DeviceEvent {
+          device_id,
+          previous_state,
+          state: Some(state),
+          timestamp: timestamp(),
+        }))
+      }
+      DeviceNotification::DefaultChanged(direction, role, device_id) => {
+        let previous = self.defaults.insert((direction, role), device_id.clone());
+        if previous.as_ref() == Some(&device_id) {
+          return None;
+        }
+        Some(DeviceChange::DefaultChanged(DefaultDeviceEvent {
+          direction,
+          role,
+          previous_id: previous.flatten(),
+          device_id,
+          timestamp: timestamp(),
+        }))
+      }
+    }
+  }
+}
+
+// 各类事件的回调，由通知线程调用
+#[derive(Clone, Default)]
+struct WatcherCallbacks {
+  added: Arc<Mutex<Option<DeviceCallback>>>,
+  removed: Arc<Mutex<Option<DeviceCallback>>>,
+  state_changed: Arc<Mutex<Option<DeviceCallback>>>,
+  default_changed: Arc<Mutex<Option<DefaultCallback>>>,
+}
+
+impl WatcherCallbacks {
+  fn dispatch(&self, change: DeviceChange) {
+    match change {
+      DeviceChange::Added(event) => call(&self.added, event),
+      DeviceChange::Removed(event) => call(&self.removed, event),
+      DeviceChange::StateChanged(event) => call(&self.state_changed, event),
+      DeviceChange::DefaultChanged(event) => call(&self.default_changed, event),
+    }
+  }
+}
+
+fn call<T>(callback: &Mutex<Option<EventCallback<T>>>, event: T) {
+  if let Ok(callback) = callback.lock() {
+    if let Some(callback) = callback.as_ref() {
+      callback(event);
+    }
+  }
+}
+
+/// Reports audio devices that are added, removed, plugged in or disabled, and changes of the
+/// default devices, without polling the device list.
+#[napi]
+#[derive(Default)]
+pub struct DeviceWatcher {
+  callbacks: WatcherCallbacks,
+  running: Arc<Mutex<bool>>,
+  worker_handle: Option<JoinHandle<()>>,
+  // 系统通知的注册，停止时注销
+  #[cfg(windows)]
+  registration: Option<EndpointNotifications>,
+}
+
+#[napi]
+impl DeviceWatcher {
+  /// Start watching the audio endpoints of the system.
+  #[napi(constructor)]
+  pub fn new() -> Result<Self> {
+    let mut watcher = DeviceWatcher::default();
+    watcher.start()?;
+    Ok(watcher)
+  }
+
+  /// Watch again after `stop`, keeping the registered callbacks. Changes that happened while
+  /// stopped are not reported.
+  #[napi]
+  pub fn start(&mut self) -> Result<()> {
+    watch_system(self)
+  }
+
+  /// Apply the notifications of `notifier` to the devices known to `tracker` on a new thread
+  /// and call the registered callbacks for every change.
+  pub fn start_with_notifier(&mut self, notifier: Box<dyn DeviceNotifier>, tracker: DeviceTracker) {
+    self.stop();
+    if let Ok(mut running) = self.running.lock() {
+      *running = true;
+    }
+
+    let callbacks = self.callbacks.clone();
+    let running = Arc::clone(&self.running);
+    self.worker_handle = Some(thread::spawn(move || {
+      watch(notifier, tracker, &callbacks, &running);
+    }));
+  }
+
+  /// Stop watching. No callbacks are called afterwards.
+  #[napi]
+  pub fn stop(&mut self) {
+    if let Ok(mut running) = self.running.lock() {
+      *running = false;
+    }
+
+    if let Some(handle) = self.worker_handle.take() {
+      let _ = handle.join();
+    }
+
+    #[cfg(windows)]
+    {
+      self.registration = None;
+    }
+  }
+
+  /// Call `callback` when a new audio device was installed.
+  #[napi(ts_args_type = "callback: (event: DeviceEvent) => void")]
+  pub fn on_device_added(&mut self, env: Env, callback: JsFunction) -> Result<()> {
+    let tsfn = event_tsfn::<DeviceEvent>(&env, callback)?;
+    set_locked(
+      &self.callbacks.added,
+      Some(Box::new(move |event| {
+        tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+      })),
+    )
+  }
+
+  /// Remove the callback registered with `onDeviceAdded`.
+  #[napi]
+  pub fn off_device_added(&mut self) -> Result<()> {
+    set_locked(&self.callbacks.added, None)
+  }
+
+  /// Call `callback` when an audio device was uninstalled.
+  #[napi(ts_args_type = "callback: (event: DeviceEvent) => void")]
+  pub fn on_device_removed(&mut self, env: Env, callback: JsFunction) -> Result<()> {
+    let tsfn = event_tsfn::<DeviceEvent>(&env, callback)?;
+    set_locked(
+      &self.callbacks.removed,
+      Some(Box::new(move |event| {
+        tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+      })),
+    )
+  }
+
+  /// Remove the callback registered with `onDeviceRemoved`.
+  #[napi]
+  pub fn off_device_removed(&mut self) -> Result<()> {
+    set_locked(&self.callbacks.removed, None)
+  }
+
+  /// Call `callback` when a device was plugged in, unplugged, enabled or disabled.
+  #[napi(ts_args_type = "callback: (event: DeviceEvent) => void")]
+  pub fn on_device_state_changed(&mut self, env: Env, callback: JsFunction) -> Result<()> {
+    let tsfn = event_tsfn::<DeviceEvent>(&env, callback)?;
+    set_locked(
+      &self.callbacks.state_changed,
+      Some(Box::new(move |event| {
+        tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+      })),
+    )
+  }
+
+  /// Remove the callback registered with `onDeviceStateChanged`.
+  #[napi]
+  pub fn off_device_state_changed(&mut self) -> Result<()> {
+    set_locked(&self.callbacks.state_changed, None)
+  }
+
+  /// Call `callback` when the default input or output device of a role changed.
+  #[napi(ts_args_type = "callback: (event: DefaultDeviceEvent) => void")]
+  pub fn on_default_changed(&mut self, env: Env, callback: JsFunction) -> Result<()> {
+    let tsfn = event_tsfn::<DefaultDeviceEvent>(&env, callback)?;
+    set_locked(
+      &self.callbacks.default_changed,
+      Some(Box::new(move |event| {
+        tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+      })),
+    )
+  }
+
+  /// Remove the callback registered with `onDefaultChanged`.
+  #[napi]
+  pub fn off_default_changed(&mut self) -> Result<()> {
+    set_locked(&self.callbacks.default_changed, None)
+  }
+}
+
+impl DeviceWatcher {
+  /// Forward the changes of the default devices to the returned receiver instead of the
+  /// `onDefaultChanged` callback, e.g. to drive a [DeviceFollower](crate::follow::DeviceFollower).
+  pub fn default_changes(&mut self) -> Result<Receiver<DeviceNotification>> {
+    let (tx, rx) = mpsc::channel();
+    set_locked(
+      &self.callbacks.default_changed,
+      Some(Box::new(move |event: DefaultDeviceEvent| {
+        let _ = tx.send(DeviceNotification::DefaultChanged(
+          event.direction,
+          event.role,
+          event.device_id,
+        ));
+      })),
+    )?;
+    Ok(rx)
+  }
+}
+
+impl Drop for DeviceWatcher {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+// 事件的线程安全回调，不阻止 Node 进程退出
+fn event_tsfn<T: ToNapiValue + 'static>(
+  env: &Env,
+  callback: JsFunction,
+) -> Result<ThreadsafeFunction<T, ErrorStrategy::Fatal>> {
+  let mut tsfn: ThreadsafeFunction<T, ErrorStrategy::Fatal> =
+    callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<T>| Ok(vec![ctx.value]))?;
+  tsfn.unref(env)?;
+  Ok(tsfn)
+}
+
+// 通知线程的主循环，停止后最多在一个等待周期内退出
+fn watch(
+  mut notifier: Box<dyn DeviceNotifier>,
+  mut tracker: DeviceTracker,
+  callbacks: &WatcherCallbacks,
+  running: &Mutex<bool>,
+) {
+  while running.lock().is_ok_and(|running| *running) {
+    let Some(notification) = notifier.next(WAIT_TIMEOUT) else {
+      continue;
+    };
+    debug!("Device notification: {:?}", notification);
+    if let Some(change) = tracker.update(notification) {
+      callbacks.dispatch(change);
+    }
+  }
+}
+
+#[cfg(windows)]
+impl From<EndpointNotification> for DeviceNotification {
+  fn from(notification: EndpointNotification) -> Self {
+    match notification {
+      EndpointNotification::Added(id) => DeviceNotification::Added(id),
+      EndpointNotification::Removed(id) => DeviceNotification::Removed(id),
+      EndpointNotification::StateChanged(id, state) => {
+        DeviceNotification::StateChanged(id, state.into())
+      }
+      EndpointNotification::DefaultChanged(direction, role, id) => {
+        DeviceNotification::DefaultChanged(direction.into(), role.into(), id)
+      }
+    }
+  }
+}
+
+// 注册系统通知并在新线程中处理，之前的注册会先被注销
+#[cfg(windows)]
+fn watch_system(watcher: &mut DeviceWatcher) -> Result<()> {
+  let _ = initialize_mta();
+  watcher.stop();
+
+  // 先注册再读取当前设备，期间的通知会在之后按已知状态去重
+  let (tx, rx) = std::sync::mpsc::channel();
+  let tx = Mutex::new(tx);
+  let registration = EndpointNotifications::register(move |notification| {
+    if let Ok(tx) = tx.lock() {
+      let _ = tx.send(DeviceNotification::from(notification));
+    }
+  })
+  .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+
+  watcher.start_with_notifier(Box::new(rx), system_devices());
+  watcher.registration = Some(registration);
+  Ok(())
+}
+
+#[cfg(not(windows))]
+fn watch_system(_watcher: &mut DeviceWatcher) -> Result<()> {
+  Err(Error::new(
+    Status::GenericFailure,
+    "Device notifications are only available on Windows",
+  ))
+}
+
+// 当前所有状态的设备和各角色的默认设备
+#[cfg(windows)]
+fn system_devices() -> DeviceTracker {
+  let mut tracker = DeviceTracker::new();
+  let states = [
+    WasapiDeviceState::Active,
+    WasapiDeviceState::Disabled,
+    WasapiDeviceState::NotPresent,
+    WasapiDeviceState::Unplugged,
+  ];
+
+  for direction in [Direction::Render, Direction::Capture] {
+    if let Ok(collection) = DeviceCollection::with_states(&direction, &states) {
+      for device in collection.into_iter().flatten() {
+        if let (Ok(id), Ok(state)) = (device.get_id(), device.get_state()) {
+          tracker.insert_device(id, state.into());
+        }
+      }
+    }
+
+    for role in [Role::Console, Role::Multimedia, Role::Communications] {
+      let device_id = get_default_device_for_role(&direction, &role)
+        .ok()
+        .and_then(|device| device.get_id().ok());
+      tracker.insert_default(direction.into(), role.into(), device_id);
+    }
+  }
+
+  tracker
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Instant;
+
+  fn tracker() -> DeviceTracker {
+    let mut tracker = DeviceTracker::new();
+    tracker.insert_device("speakers".to_string(), DeviceState::Active);
+    tracker.insert_device("headphones".to_string(), DeviceState::Unplugged);
+    tracker.insert_default(
+      DeviceDirection::Render,
+      DeviceRole::Console,
+      Some("speakers".to_string()),
+    );
+    tracker
+  }
+
+  #[test]
+  fn drops_notifications_that_change_nothing() {
+    use DeviceNotification::*;
+    let mut tracker = tracker();
+    let default = |id: &str| {
+      DefaultChanged(
+        DeviceDirection::Render,
+        DeviceRole::Console,
+        Some(id.to_string()),
+      )
+    };
+
+    match tracker.update(StateChanged("headphones".into(), DeviceState::Active)) {
+      Some(DeviceChange::StateChanged(event)) => {
+        assert_eq!(event.previous_state, Some(DeviceState::Unplugged));
+        assert_eq!(event.state, Some(DeviceState::Active));
+      }
+      change => panic!("unexpected {:?}", change),
+    }
+    assert_eq!(
+      tracker.update(StateChanged("headphones".into(), DeviceState::Active)),
+      None
+    );
+
+    match tracker.update(default("headphones")) {
+      Some(DeviceChange::DefaultChanged(event)) => {
+        assert_eq!(event.previous_id.as_deref(), Some("speakers"));
+        assert_eq!(event.device_id.as_deref(), Some("headphones"));
+      }
+      change => panic!("unexpected {:?}", change),
+    }
+    assert_eq!(tracker.update(default("headphones")), None);
+
+    assert!(matches!(
+      tracker.update(Added("usb".into())),
+      Some(DeviceChange::Added(_))
+    ));
+    assert_eq!(tracker.update(Added("usb".into())), None);
+    assert!(matches!(
+      tracker.update(Removed("usb".into())),
+      Some(DeviceChange::Removed(_))
+    ));
+    assert_eq!(tracker.update(Removed("usb".into())), None);
+  }
+
+  #[test]
+  fn dispatches_changes_to_the_callbacks() {
+    use DeviceNotification::*;
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let record = |name: &'static str| {
+      let events = Arc::clone(&events);
+      Box::new(move |event: DeviceEvent| {
+        events
+          .lock()
+          .unwrap()
+          .push(format!("{} {}", name, event.device_id));
+      })
+    };
+
+    let mut watcher = DeviceWatcher::default();
+    set_locked(&watcher.callbacks.added, Some(record("added"))).unwrap();
+    set_locked(&watcher.callbacks.removed, Some(record("removed"))).unwrap();
+    set_locked(&watcher.callbacks.state_changed, Some(record("state"))).unwrap();
+    let defaults = watcher.default_changes().unwrap();
+
+    let script = vec![
+      StateChanged("headphones".into(), DeviceState::Active),
+      DefaultChanged(
+        DeviceDirection::Render,
+        DeviceRole::Console,
+        Some("headphones".into()),
+      ),
+      DefaultChanged(
+        DeviceDirection::Render,
+        DeviceRole::Console,
+        Some("headphones".into()),
+      ),
+      Added("usb".into()),
+      Removed("speakers".into()),
+    ];
+    watcher.start_with_notifier(Box::new(ScriptedNotifier(script.into())), tracker());
+
+    let started = Instant::now();
+    while events.lock().unwrap().len() < 3 && started.elapsed() < Duration::from_secs(2) {
+      thread::sleep(Duration::from_millis(10));
+    }
+    watcher.stop();
+
+    assert_eq!(
+      *events.lock().unwrap(),
+      vec!["state headphones", "added usb", "removed speakers"]
+    );
+    // 重复的默认设备通知只转发一次
+    assert_eq!(
+      defaults.try_iter().collect::<Vec<_>>(),
+      vec![DefaultChanged(
+        DeviceDirection::Render,
+        DeviceRole::Console,
+        Some("headphones".into()),
+      )]
+    );
+
+    // 停止后可以重新开始，回调保持注册
+    let script = vec![Added("dock".into())];
+    watcher.start_with_notifier(Box::new(ScriptedNotifier(script.into())), tracker());
+    let started = Instant::now();
+    while events.lock().unwrap().len() < 4 && started.elapsed() < Duration::from_secs(2) {
+      thread::sleep(Duration::from_millis(10));
+    }
+    watcher.stop();
+    assert_eq!(events.lock().unwrap().last().unwrap(), "added dock");
+  }
+}
@@ -0,0 +1,98 @@
+use crate::types::FrequencyWeighting;
+
+/// Gain of the weighting curve at `frequency` Hz, in dB. Minus infinity at 0 Hz for the
+/// curves that reject DC.
+pub fn weighting_db(weighting: FrequencyWeighting, frequency: f32) -> f32 {
+  let f = frequency as f64;
+  let f2 = f * f;
+  let db = match weighting {
+    FrequencyWeighting::Z => return 0.0,
+    // IEC 61672-1
+    FrequencyWeighting::A => {
+      let response = 12194f64.powi(2) * f2 * f2
+        / ((f2 + 20.6f64.powi(2))
+          * ((f2 + 107.7f64.powi(2)) * (f2 + 737.9f64.powi(2))).sqrt()
+          * (f2 + 12194f64.powi(2)));
+      20.0 * response.log10() + 2.0
+    }
+    FrequencyWeighting::C => {
+      let response = 12194f64.powi(2) * f2 / ((f2 + 20.6f64.powi(2)) * (f2 + 12194f64.powi(2)));
+      20.0 * response.log10() + 0.06
+    }
+    // ITU-R BS.468-4
+    FrequencyWeighting::Itu468 => {
+      let h1 = -4.737_338_981_378_384e-24 * f2 * f2 * f2 + 2.043_828_333_606_125e-15 * f2 * f2
+        - 1.363_894_795_463_638e-7 * f2
+        + 1.0;
+      let h2 = 1.306_612_257_412_824e-19 * f2 * f2 * f - 2.118_150_887_518_656e-11 * f2 * f
+        + 5.559_488_023_498_642e-4 * f;
+      let response = 1.246_332_637_532_143e-4 * f / (h1 * h1 + h2 * h2).sqrt();
+      20.0 * response.log10() + 18.2
+    }
+  };
+  db as f32
+}
+
+/// Linear amplitude gain of the weighting curve at `frequency` Hz.
+pub fn weighting_gain(weighting: FrequencyWeighting, frequency: f32) -> f32 {
+  10f32.powf(weighting_db(weighting, frequency) / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_curve(weighting: FrequencyWeighting, expected: &[(f32, f32)]) {
+    for &(frequency, db) in expected {
+      let measured = weighting_db(weighting, frequency);
+      assert!(
+        (measured - db).abs() < 0.1,
+        "{:?} at {} Hz: {}",
+        weighting,
+        frequency,
+        measured
+      );
+    }
+  }
+
+  #[test]
+  fn curves_match_reference_tables() {
+    assert_curve(
+      FrequencyWeighting::A,
+      &[
+        (31.5, -39.5),
+        (100.0, -19.1),
+        (1000.0, 0.0),
+        (4000.0, 1.0),
+        (10000.0, -2.5),
+      ],
+    );
+    assert_curve(
+      FrequencyWeighting::C,
+      &[(31.5, -3.0), (100.0, -0.3), (1000.0, 0.0), (10000.0, -4.4)],
+    );
+    assert_curve(
+      FrequencyWeighting::Itu468,
+      &[
+        (100.0, -19.8),
+        (1000.0, 0.0),
+        (2000.0, 5.6),
+        (6300.0, 12.2),
+        (10000.0, 8.1),
+      ],
+    );
+    assert_curve(FrequencyWeighting::Z, &[(10.0, 0.0), (1000.0, 0.0)]);
+  }
+
+  #[test]
+  fn dc_is_rejected() {
+    for weighting in [
+      FrequencyWeighting::A,
+      FrequencyWeighting::C,
+      FrequencyWeighting::Itu468,
+    ] {
+      assert_eq!(weighting_gain(weighting, 0.0), 0.0, "{:?}", weighting);
+    }
+    assert_eq!(weighting_gain(FrequencyWeighting::Z, 0.0), 1.0);
+  }
+}
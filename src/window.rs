@@ -0,0 +1,285 @@
+use std::f64::consts::PI;
+
+use crate::types::WindowFunction;
+use crate::utils::check_value;
+use napi::Result;
+
+/// Largest Kaiser beta accepted by `setWindow`. Its side lobes are already far below what
+/// f32 samples can resolve.
+pub const MAX_KAISER_BETA: f64 = 50.0;
+
+/// Window function applied to each block before the FFT.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Window {
+  Rectangular,
+  Hann,
+  Hamming,
+  Blackman,
+  /// 4-term Blackman-Harris, -92 dB side lobes.
+  BlackmanHarris,
+  /// Flat-top window with negligible scalloping loss, for accurate amplitudes.
+  FlatTop,
+  /// Kaiser-Bessel window, larger `beta` trades resolution for lower side lobes.
+  Kaiser {
+    beta: f32,
+  },
+  /// Gaussian window, `sigma` is the standard deviation relative to half the window length.
+  Gaussian {
+    sigma: f32,
+  },
+}
+
+impl Window {
+  /// Window selected with `setWindow`. `parameter` is beta for Kaiser windows, between 0 and
+  /// [MAX_KAISER_BETA], and sigma for Gaussian windows, above 0.
+  pub fn from_options(window: WindowFunction, parameter: Option<f64>) -> Result<Self> {
+    Ok(match window {
+      WindowFunction::Rectangular => Window::Rectangular,
+      WindowFunction::Hann => Window::Hann,
+      WindowFunction::Hamming => Window::Hamming,
+      WindowFunction::Blackman => Window::Blackman,
+      WindowFunction::BlackmanHarris => Window::BlackmanHarris,
+      WindowFunction::FlatTop => Window::FlatTop,
+      WindowFunction::Kaiser => {
+        check_value(
+          "Kaiser beta",
+          parameter,
+          |beta| (0.0..=MAX_KAISER_BETA).contains(&beta),
+          " between 0 and 50",
+        )?;
+        Window::Kaiser {
+          beta: parameter.unwrap_or(8.6) as f32,
+        }
+      }
+      WindowFunction::Gaussian => {
+        check_value("Gaussian sigma", parameter, |sigma| sigma > 0.0, " above 0")?;
+        Window::Gaussian {
+          sigma: parameter.unwrap_or(0.4) as f32,
+        }
+      }
+    })
+  }
+
+  /// Symmetric window table of `size` coefficients.
+  pub fn coefficients(&self, size: usize) -> Vec<f32> {
+    if size <= 1 {
+      return vec![1.0; size];
+    }
+
+    let last = (size - 1) as f64;
+    (0..size)
+      .map(|n| {
+        let x = n as f64 / last;
+        let value = match *self {
+          Window::Rectangular => 1.0,
+          Window::Hann => cosine_sum(x, &[0.5, 0.5]),
+          Window::Hamming => cosine_sum(x, &[0.54, 0.46]),
+          Window::Blackman => cosine_sum(x, &[0.42, 0.5, 0.08]),
+          Window::BlackmanHarris => cosine_sum(x, &[0.35875, 0.48829, 0.14128, 0.01168]),
+          Window::FlatTop => cosine_sum(
+            x,
+            &[
+              0.215_578_95,
+              0.416_631_58,
+              0.277_263_158,
+              0.083_578_947,
+              0.006_947_368,
+            ],
+          ),
+          Window::Kaiser { beta } => {
+            // 取对数后相减，较大的 beta 也不会溢出
+            let beta = (beta as f64).max(0.0);
+            let r = 2.0 * x - 1.0;
+            (ln_bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) - ln_bessel_i0(beta)).exp()
+          }
+          Window::Gaussian { sigma } => {
+            let r = (2.0 * x - 1.0) / (sigma as f64).max(1e-3);
+            (-0.5 * r * r).exp()
+          }
+        };
+        value as f32
+      })
+      .collect()
+  }
+}
+
+/// Mean of the window coefficients, i.e. the amplitude a full scale sine keeps after windowing.
+pub fn coherent_gain(coefficients: &[f32]) -> f32 {
+  if coefficients.is_empty() {
+    return 1.0;
+  }
+  coefficients.iter().sum::<f32>() / coefficients.len() as f32
+}
+
+/// Equivalent noise bandwidth in bins, i.e. how much a coherent-gain compensated spectrum
+/// over-reads the power of broadband signals.
+pub fn noise_bandwidth(coefficients: &[f32]) -> f32 {
+  let sum = coefficients.iter().sum::<f32>();
+  if sum == 0.0 {
+    return 1.0;
+  }
+  let squares = coefficients.iter().map(|w| w * w).sum::<f32>();
+  coefficients.len() as f32 * squares / (sum * sum)
+}
+
+// 广义余弦窗：a0 - a1 cos(2πx) + a2 cos(4πx) - ...
+fn cosine_sum(x: f64, terms: &[f64]) -> f64 {
+  terms
+    .iter()
+    .enumerate()
+    .map(|(k, a)| {
+      let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+      sign * a * (2.0 * PI * k as f64 * x).cos()
+    })
+    .sum()
+}
+
+// 第一类零阶修正贝塞尔函数的自然对数。小参数用级数展开，大参数用渐近展开
+fn ln_bessel_i0(x: f64) -> f64 {
+  if x < 30.0 {
+    return bessel_i0(x).ln();
+  }
+  let t = 1.0 / (8.0 * x);
+  x - 0.5 * (2.0 * PI * x).ln() + (1.0 + t * (1.0 + t * (4.5 + t * 37.5))).ln()
+}
+
+// 第一类零阶修正贝塞尔函数，级数展开，参数在 30 以内时收敛
+fn bessel_i0(x: f64) -> f64 {
+  let half = x / 2.0;
+  let mut term = 1.0;
+  let mut sum = 1.0;
+  for k in 1..64 {
+    term *= half / k as f64;
+    let value = term * term;
+    sum += value;
+    if value < sum * 1e-12 {
+      break;
+    }
+  }
+  sum
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ALL: [Window; 8] = [
+    Window::Rectangular,
+    Window::Hann,
+    Window::Hamming,
+    Window::Blackman,
+    Window::BlackmanHarris,
+    Window::FlatTop,
+    Window::Kaiser { beta: 8.6 },
+    Window::Gaussian { sigma: 0.4 },
+  ];
+
+  #[test]
+  fn windows_are_symmetric_and_peak_in_the_centre() {
+    for window in ALL {
+      let table = window.coefficients(1025);
+      for (a, b) in table.iter().zip(table.iter().rev()) {
+        assert!((a - b).abs() < 1e-5, "{:?}", window);
+      }
+      assert!((table[512] - 1.0).abs() < 1e-3, "{:?}", window);
+    }
+  }
+
+  #[test]
+  fn coherent_gains_match_reference_values() {
+    let expected = [
+      (Window::Rectangular, 1.0),
+      (Window::Hann, 0.5),
+      (Window::Hamming, 0.54),
+      (Window::Blackman, 0.42),
+      (Window::BlackmanHarris, 0.35875),
+      (Window::FlatTop, 0.2156),
+    ];
+    for (window, gain) in expected {
+      let measured = coherent_gain(&window.coefficients(4096));
+      assert!((measured - gain).abs() < 1e-3, "{:?}: {}", window, measured);
+    }
+  }
+
+  #[test]
+  fn noise_bandwidths_match_reference_values() {
+    let expected = [
+      (Window::Rectangular, 1.0),
+      (Window::Hann, 1.5),
+      (Window::Hamming, 1.363),
+      (Window::Blackman, 1.727),
+      (Window::BlackmanHarris, 2.004),
+    ];
+    for (window, bandwidth) in expected {
+      let measured = noise_bandwidth(&window.coefficients(4096));
+      assert!(
+        (measured - bandwidth).abs() < 2e-3,
+        "{:?}: {}",
+        window,
+        measured
+      );
+    }
+  }
+
+  #[test]
+  fn kaiser_matches_known_values() {
+    assert!((bessel_i0(0.0) - 1.0).abs() < 1e-12);
+    assert!((bessel_i0(1.0) - 1.266_065_877_752_008_4).abs() < 1e-12);
+
+    // beta = 0 退化为矩形窗
+    let table = Window::Kaiser { beta: 0.0 }.coefficients(16);
+    assert!(table.iter().all(|&v| (v - 1.0).abs() < 1e-6));
+  }
+
+  #[test]
+  fn large_kaiser_betas_stay_finite() {
+    // 渐近展开与级数在分界处衔接
+    let (below, above) = (bessel_i0(29.999_999).ln(), ln_bessel_i0(30.0));
+    assert!((below - above).abs() < 1e-6, "{} {}", below, above);
+
+    for beta in [30.0, 50.0, 1000.0] {
+      let table = Window::Kaiser { beta }.coefficients(1025);
+      assert!(table.iter().all(|v| v.is_finite()), "{}", beta);
+      assert!((table[512] - 1.0).abs() < 1e-6, "{}", beta);
+      assert!(table[0] < 1e-9, "{}", beta);
+    }
+  }
+
+  #[test]
+  fn window_options_are_validated() {
+    for (window, parameter, message) in [
+      (
+        WindowFunction::Kaiser,
+        -1.0,
+        "Kaiser beta -1 must be a finite number between 0 and 50",
+      ),
+      (
+        WindowFunction::Kaiser,
+        1000.0,
+        "Kaiser beta 1000 must be a finite number between 0 and 50",
+      ),
+      (
+        WindowFunction::Gaussian,
+        0.0,
+        "Gaussian sigma 0 must be a finite number above 0",
+      ),
+    ] {
+      let err = Window::from_options(window, Some(parameter)).unwrap_err();
+      assert_eq!(err.reason, message);
+    }
+
+    assert_eq!(
+      Window::from_options(WindowFunction::Kaiser, Some(50.0)).unwrap(),
+      Window::Kaiser { beta: 50.0 }
+    );
+    assert_eq!(
+      Window::from_options(WindowFunction::Gaussian, None).unwrap(),
+      Window::Gaussian { sigma: 0.4 }
+    );
+    // 其他窗函数忽略参数
+    assert_eq!(
+      Window::from_options(WindowFunction::Hann, Some(f64::NAN)).unwrap(),
+      Window::Hann
+    );
+  }
+}